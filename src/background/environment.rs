@@ -0,0 +1,191 @@
+use std::f64::consts::PI;
+use std::io;
+use std::path::Path;
+
+use super::{from_lat_long, to_lat_long, Background};
+use crate::distribution::Distribution2D;
+use crate::geometry::Vec3;
+use crate::sampler::Sampler;
+use crate::texture::ImageTexture;
+
+/// An equirectangular (latitude-longitude) image surrounding the scene.
+///
+/// The top row is straight up (+y), the center of the image looks down -z.
+/// Directions are importance sampled proportionally to the luminance of the image.
+#[derive(Debug, Clone)]
+pub struct EnvironmentMap {
+    image: ImageTexture,
+    distribution: Distribution2D,
+    /// Turns the environment around the y axis, in radians
+    pub rotation: f64,
+    /// Multiplies the radiance of the image
+    pub intensity: f64,
+}
+
+impl EnvironmentMap {
+    pub fn new(image: ImageTexture) -> Self {
+        let (width, height) = (image.width(), image.height());
+
+        // Rows near the poles cover less solid angle
+        let mut func = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let sin_theta = (PI * (y as f64 + 0.5) / height as f64).sin();
+            for x in 0..width {
+                let c = image.texel(x as i64, y as i64);
+                func.push(sin_theta * c.luminance().max(0.0));
+            }
+        }
+
+        Self {
+            image,
+            distribution: Distribution2D::new(&func, width, height),
+            rotation: 0.0,
+            intensity: 1.0,
+        }
+    }
+
+    /// Loads a `.hdr` or `.pfm` image, any other extension is read as PNG
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let image = match extension.as_deref() {
+            Some("hdr") => ImageTexture::open_hdr(path)?,
+            Some("pfm") => ImageTexture::open_pfm(path)?,
+            _ => ImageTexture::open(path)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+        };
+        Ok(Self::new(image))
+    }
+
+    fn y_axis() -> Vec3 {
+        Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        }
+    }
+
+    /// Image coordinates of a world direction, `t` increasing downwards
+    fn to_image(&self, dir: Vec3) -> (f64, f64) {
+        to_lat_long(dir.rotate_axis_angle(Self::y_axis(), -self.rotation))
+    }
+
+    /// World direction of image coordinates along with the sine of its polar angle
+    fn to_direction(&self, s: f64, t: f64) -> (Vec3, f64) {
+        let (d, sin_theta) = from_lat_long(s, t);
+        (
+            d.rotate_axis_angle(Self::y_axis(), self.rotation),
+            sin_theta,
+        )
+    }
+
+    fn lookup(&self, s: f64, t: f64) -> Vec3 {
+        self.intensity * self.image.sample(s, 1.0 - t)
+    }
+}
+
+impl Background for EnvironmentMap {
+    fn value(&self, dir: Vec3) -> Vec3 {
+        let (s, t) = self.to_image(dir);
+        self.lookup(s, t)
+    }
+
+    fn sample(&self, sampler: &mut dyn Sampler) -> Option<(Vec3, Vec3, f64)> {
+        let ((s, t), pdf) = self.distribution.sample(sampler.get_2d());
+        let (dir, sin_theta) = self.to_direction(s, t);
+        if pdf <= 0.0 || sin_theta <= 0.0 {
+            return None;
+        }
+
+        // Change of variables from the unit square to solid angle
+        let pdf = pdf / (2.0 * PI * PI * sin_theta);
+        Some((dir, self.lookup(s, t), pdf))
+    }
+
+    fn pdf(&self, dir: Vec3) -> f64 {
+        let (s, t) = self.to_image(dir);
+        let sin_theta = (PI * t).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(s, t) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+    use crate::texture::Filter;
+
+    fn bright_spot() -> EnvironmentMap {
+        // Dim everywhere but one texel
+        let (width, height) = (16, 8);
+        let mut texels = vec![Vec3::splat(0.1); width * height];
+        texels[3 * width + 12] = Vec3::splat(100.0);
+        let mut image = ImageTexture::new(width, height, texels);
+        image.filter = Filter::Nearest;
+        EnvironmentMap::new(image)
+    }
+
+    #[test]
+    fn mapping() {
+        let mut env = bright_spot();
+        env.rotation = 0.7;
+        for (s, t) in [(0.1, 0.2), (0.5, 0.5), (0.8, 0.9)].iter() {
+            let (dir, _) = env.to_direction(*s, *t);
+            let (s2, t2) = env.to_image(dir);
+            assert!((s - s2).abs() < 1e-9 && (t - t2).abs() < 1e-9);
+        }
+
+        // The image center looks down -z
+        env.rotation = 0.0;
+        let (dir, _) = env.to_direction(0.5, 0.5);
+        assert!(
+            (dir - Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: -1.0
+            })
+            .len()
+                < 1e-12
+        );
+    }
+
+    #[test]
+    fn importance_sampling() {
+        let env = bright_spot();
+        let mut rng = Rng::new(7);
+
+        // Samples land on the bright texel most of the time and report the density `pdf` gives
+        let mut hits = 0;
+        for _ in 0..1000 {
+            let (dir, radiance, pdf) = env.sample(&mut rng).unwrap();
+            assert!((pdf - env.pdf(dir)).abs() < 1e-6 * pdf);
+            if radiance.x > 1.0 {
+                hits += 1;
+            }
+        }
+        assert!(hits > 900);
+
+        // Estimating the total radiance with importance sampling
+        let n = 20000;
+        let mut sum = 0.0;
+        for _ in 0..n {
+            let (_, radiance, pdf) = env.sample(&mut rng).unwrap();
+            sum += radiance.x / pdf;
+        }
+        let mut reference = 0.0;
+        let m = 400;
+        for i in 0..m {
+            for j in 0..m {
+                let (s, t) = ((i as f64 + 0.5) / m as f64, (j as f64 + 0.5) / m as f64);
+                let (dir, sin_theta) = env.to_direction(s, t);
+                reference += env.value(dir).x * sin_theta * 2.0 * PI * PI / (m * m) as f64;
+            }
+        }
+        assert!((sum / n as f64 - reference).abs() < 0.05 * reference);
+    }
+}
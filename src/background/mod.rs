@@ -0,0 +1,145 @@
+mod environment;
+mod sky;
+
+use std::f64::consts::PI;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::geometry::Vec3;
+use crate::sampler::Sampler;
+use crate::texture::Texture;
+
+pub use environment::EnvironmentMap;
+pub use sky::Sky;
+
+/// Radiance arriving from infinitely far away, seen by rays that leave the scene
+pub trait Background: Send + Sync + Debug {
+    /// Radiance arriving along the unit direction `dir`, pointing away from the scene
+    fn value(&self, dir: Vec3) -> Vec3;
+
+    /// Picks a direction to sample lighting from, returning it with its radiance
+    /// and density in solid angle. Backgrounds that return `None` are only
+    /// found by paths escaping the scene.
+    fn sample(&self, _sampler: &mut dyn Sampler) -> Option<(Vec3, Vec3, f64)> {
+        None
+    }
+
+    /// Density `sample` picks the unit direction `dir` with
+    fn pdf(&self, _dir: Vec3) -> f64 {
+        0.0
+    }
+}
+
+/// A constant color in every direction
+impl Background for Vec3 {
+    fn value(&self, _dir: Vec3) -> Vec3 {
+        *self
+    }
+}
+
+/// Blends from `bottom` looking straight down to `top` looking straight up
+#[derive(Debug, Clone)]
+pub struct Gradient {
+    pub bottom: Vec3,
+    pub top: Vec3,
+}
+
+impl Gradient {
+    /// The white to light blue sky of the original book
+    pub fn sky() -> Self {
+        Self {
+            bottom: Vec3::splat(1.0),
+            top: Vec3 {
+                x: 0.5,
+                y: 0.7,
+                z: 1.0,
+            },
+        }
+    }
+}
+
+impl Background for Gradient {
+    fn value(&self, dir: Vec3) -> Vec3 {
+        let t = 0.5 * (dir.y + 1.0);
+        (1.0 - t) * self.bottom + t * self.top
+    }
+}
+
+/// Any texture wrapped around the scene. It is looked up with the latitude-longitude
+/// coordinates of the direction as `(u, v)`, `v` increasing upwards, and the direction as `p`.
+#[derive(Debug, Clone)]
+pub struct TextureBackground {
+    pub texture: Arc<dyn Texture>,
+}
+
+impl Background for TextureBackground {
+    fn value(&self, dir: Vec3) -> Vec3 {
+        let (s, t) = to_lat_long(dir);
+        self.texture.value(s, 1.0 - t, dir)
+    }
+}
+
+/// Latitude-longitude coordinates of a unit direction. `s` turns around the
+/// y axis with 0.5 looking down -z, `t` runs from 0 straight up to 1 straight down.
+fn to_lat_long(d: Vec3) -> (f64, f64) {
+    let s = 0.5 + d.x.atan2(-d.z) / (2.0 * PI);
+    let t = d.y.clamp(-1.0, 1.0).acos() / PI;
+    (s - s.floor(), t)
+}
+
+/// Direction of latitude-longitude coordinates along with the sine of its polar angle
+fn from_lat_long(s: f64, t: f64) -> (Vec3, f64) {
+    let (sin_theta, cos_theta) = (PI * t).sin_cos();
+    let (sin_phi, cos_phi) = (2.0 * PI * (s - 0.5)).sin_cos();
+    let d = Vec3 {
+        x: sin_theta * sin_phi,
+        y: cos_theta,
+        z: -sin_theta * cos_phi,
+    };
+    (d, sin_theta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::{LinearGradient, TextureSpace};
+
+    #[test]
+    fn backgrounds() {
+        let up = Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        };
+        let sky = Gradient::sky();
+        assert_eq!(sky.value(up), sky.top);
+        assert_eq!(sky.value(-up), sky.bottom);
+
+        // A texture varying with u turns around the y axis
+        let texture = TextureBackground {
+            texture: Arc::new(LinearGradient {
+                space: TextureSpace::Uv,
+                start: Vec3::zero(),
+                end: Vec3 {
+                    x: 1.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                from: Vec3::zero(),
+                to: Vec3::splat(1.0),
+            }),
+        };
+        let forward = Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: -1.0,
+        };
+        let right = Vec3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        assert_eq!(texture.value(forward), Vec3::splat(0.5));
+        assert_eq!(texture.value(right), Vec3::splat(0.75));
+    }
+}
@@ -0,0 +1,278 @@
+use std::f64::consts::PI;
+
+use super::Background;
+use crate::geometry::Vec3;
+use crate::sampler::Sampler;
+use crate::spectrum;
+
+/// Angular radius of the sun disk, in radians
+const SUN_RADIUS: f64 = 0.00465;
+/// Luminance of the sun outside the atmosphere, in kcd/m²
+const SUN_LUMINANCE: f64 = 1.6e6;
+
+/// Daylight sky from the Preetham, Shirley and Smits analytic model, plus the sun disk.
+///
+/// Radiance is in kcd/m² scaled by `intensity`; the default scale puts a clear
+/// noon sky around 0.5. Directions below the horizon see the sky at the
+/// horizon. The sun disk is importance sampled.
+#[derive(Debug, Clone)]
+pub struct Sky {
+    sun: Vec3,
+    /// Perez coefficients A to E for Y, x and y
+    perez: [[f64; 5]; 3],
+    /// Y, x, y of the zenith
+    zenith: [f64; 3],
+    sun_radiance: Vec3,
+    /// Multiplies the radiance of the sky and sun
+    pub intensity: f64,
+}
+
+impl Sky {
+    /// `sun` points towards the sun, `turbidity` ranges from 2 (very clear) to about 10 (hazy)
+    pub fn new(sun: Vec3, turbidity: f64) -> Self {
+        let sun = sun.unit();
+        let t = turbidity;
+        let theta_s = sun.y.clamp(0.0, 1.0).acos();
+
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let chromaticity = |m: [[f64; 4]; 3]| {
+            let th = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+            let row = |r: [f64; 4]| r.iter().zip(th.iter()).map(|(a, b)| a * b).sum::<f64>();
+            t * t * row(m[0]) + t * row(m[1]) + row(m[2])
+        };
+        let zenith_x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_yc = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        Self {
+            sun,
+            perez,
+            zenith: [zenith_y.max(0.0), zenith_x, zenith_yc],
+            sun_radiance: SUN_LUMINANCE * sun_transmittance(theta_s, t),
+            intensity: 0.05,
+        }
+    }
+
+    /// Sun placed by its elevation above the horizon and its azimuth from -z towards +x, in radians
+    pub fn from_angles(elevation: f64, azimuth: f64, turbidity: f64) -> Self {
+        let (sin_e, cos_e) = elevation.sin_cos();
+        let (sin_a, cos_a) = azimuth.sin_cos();
+        Self::new(
+            Vec3 {
+                x: cos_e * sin_a,
+                y: sin_e,
+                z: -cos_e * cos_a,
+            },
+            turbidity,
+        )
+    }
+
+    /// Sky radiance without the sun disk
+    pub fn sky(&self, dir: Vec3) -> Vec3 {
+        let cos_theta = dir.y.max(0.01);
+        let cos_gamma = Vec3::dot(dir, self.sun).clamp(-1.0, 1.0);
+        let gamma = cos_gamma.acos();
+        let theta_s = self.sun.y.clamp(0.0, 1.0).acos();
+
+        let perez = |c: [f64; 5], cos_theta: f64, gamma: f64, cos_gamma: f64| {
+            (1.0 + c[0] * (c[1] / cos_theta).exp())
+                * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * cos_gamma * cos_gamma)
+        };
+        let relative = |i: usize| {
+            perez(self.perez[i], cos_theta, gamma, cos_gamma)
+                / perez(self.perez[i], 1.0, theta_s, theta_s.cos())
+        };
+
+        let lum = self.zenith[0] * relative(0);
+        let x = self.zenith[1] * relative(1);
+        let y = self.zenith[2] * relative(2);
+        if y <= 0.0 {
+            return Vec3::zero();
+        }
+
+        let xyz = Vec3 {
+            x: x / y * lum,
+            y: lum,
+            z: (1.0 - x - y) / y * lum,
+        };
+        let rgb = spectrum::xyz_to_rgb(xyz);
+        Vec3 {
+            x: rgb.x.max(0.0),
+            y: rgb.y.max(0.0),
+            z: rgb.z.max(0.0),
+        }
+    }
+
+    fn cos_sun_radius() -> f64 {
+        SUN_RADIUS.cos()
+    }
+
+    fn sun_visible(&self) -> bool {
+        self.sun.y > -SUN_RADIUS
+    }
+}
+
+impl Background for Sky {
+    fn value(&self, dir: Vec3) -> Vec3 {
+        let mut radiance = self.sky(dir);
+        if self.sun_visible() && Vec3::dot(dir, self.sun) >= Self::cos_sun_radius() {
+            radiance += self.sun_radiance;
+        }
+        self.intensity * radiance
+    }
+
+    fn sample(&self, sampler: &mut dyn Sampler) -> Option<(Vec3, Vec3, f64)> {
+        if !self.sun_visible() {
+            return None;
+        }
+
+        // Uniform over the cone the sun disk covers
+        let cos_max = Self::cos_sun_radius();
+        let (u1, u2) = sampler.get_2d();
+        let cos_theta = 1.0 - u1 * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let phi = 2.0 * PI * u2;
+
+        let w = self.sun;
+        let a = if w.x.abs() > 0.9 {
+            Vec3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            }
+        } else {
+            Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            }
+        };
+        let u = Vec3::cross(a, w).unit();
+        let v = Vec3::cross(w, u);
+        let dir = (sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * w).unit();
+
+        Some((
+            dir,
+            self.intensity * (self.sky(dir) + self.sun_radiance),
+            1.0 / (2.0 * PI * (1.0 - cos_max)),
+        ))
+    }
+
+    fn pdf(&self, dir: Vec3) -> f64 {
+        let cos_max = Self::cos_sun_radius();
+        if self.sun_visible() && Vec3::dot(dir, self.sun) >= cos_max {
+            1.0 / (2.0 * PI * (1.0 - cos_max))
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Fraction of sunlight making it through the atmosphere for red, green and
+/// blue, from Rayleigh and aerosol scattering along the optical path
+fn sun_transmittance(theta_s: f64, turbidity: f64) -> Vec3 {
+    // Relative optical mass, Kasten's approximation
+    let elevation_deg = 93.885 - theta_s.to_degrees();
+    let m = 1.0 / (theta_s.cos() + 0.15 * elevation_deg.max(1e-3).powf(-1.253));
+
+    let beta = 0.04608 * turbidity - 0.04586;
+    let tau = |lambda_um: f64| {
+        let rayleigh = 0.008735 * lambda_um.powf(-4.08);
+        let aerosol = beta * lambda_um.powf(-1.3);
+        (-m * (rayleigh + aerosol)).exp()
+    };
+
+    Vec3 {
+        x: tau(0.65),
+        y: tau(0.55),
+        z: tau(0.45),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    #[test]
+    fn sky() {
+        let sky = Sky::from_angles(0.5, 0.0, 3.0);
+        let up = Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        };
+
+        // Zenith matches the model's zenith luminance and the sky is blue
+        let zenith = sky.sky(up);
+        let lum = zenith.luminance();
+        assert!((lum - sky.zenith[0]).abs() < 0.05 * sky.zenith[0]);
+        assert!(zenith.z > zenith.x);
+
+        // Brighter around the sun than opposite to it
+        let towards = Vec3 {
+            x: 0.0,
+            y: 0.3,
+            z: -1.0,
+        }
+        .unit();
+        let away = Vec3 {
+            x: 0.0,
+            y: 0.3,
+            z: 1.0,
+        }
+        .unit();
+        assert!(sky.sky(towards).y > sky.sky(away).y);
+
+        // The sun reddens towards the horizon
+        let low = sun_transmittance(1.5, 3.0);
+        let high = sun_transmittance(0.2, 3.0);
+        assert!(low.x / low.z > high.x / high.z);
+    }
+
+    #[test]
+    fn sun_sampling() {
+        let sky = Sky::from_angles(0.5, 1.0, 3.0);
+        let mut rng = Rng::new(3);
+        for _ in 0..100 {
+            let (dir, radiance, pdf) = sky.sample(&mut rng).unwrap();
+            assert!(pdf > 0.0 && pdf == sky.pdf(dir));
+            assert!(radiance.y > sky.intensity * sky.sky(dir).y);
+        }
+
+        let night = Sky::from_angles(-0.2, 0.0, 3.0);
+        assert!(night.sample(&mut rng).is_none());
+    }
+}
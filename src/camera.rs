@@ -1,5 +1,5 @@
-use crate::geometry::{Ray, Vec3};
-use crate::rng::Rng;
+use crate::geometry::{Ray, RayDiff, Vec3};
+use crate::sampler::Sampler;
 
 #[derive(Debug)]
 pub struct Camera {
@@ -50,15 +50,49 @@ impl Camera {
         }
     }
 
-    pub fn get_ray(&self, s: f64, t: f64, rng: &mut Rng) -> Ray {
-        let rd = self.lens_radius * Vec3::random_unit_disk(rng);
-        let offset = self.u * rd.x + self.v * rd.y;
+    pub fn get_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Ray {
+        let offset = self.lens_offset(sampler);
 
         Ray {
             o: self.origin + offset,
-            d: self.top_right + (s * self.horizontal) - (t * self.vertical) - self.origin - offset,
+            d: self.direction(s, t, offset),
+            diff: None,
         }
     }
+
+    /// Same as `get_ray` but also traces the rays offset by `ds` and `dt`,
+    /// through the same point on the lens.
+    pub fn get_ray_differential(
+        &self,
+        s: f64,
+        t: f64,
+        ds: f64,
+        dt: f64,
+        sampler: &mut dyn Sampler,
+    ) -> Ray {
+        let offset = self.lens_offset(sampler);
+        let o = self.origin + offset;
+
+        Ray {
+            o,
+            d: self.direction(s, t, offset),
+            diff: Some(RayDiff {
+                rx_o: o,
+                rx_d: self.direction(s + ds, t, offset),
+                ry_o: o,
+                ry_d: self.direction(s, t + dt, offset),
+            }),
+        }
+    }
+
+    fn lens_offset(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let rd = self.lens_radius * Vec3::random_unit_disk(sampler);
+        self.u * rd.x + self.v * rd.y
+    }
+
+    fn direction(&self, s: f64, t: f64, offset: Vec3) -> Vec3 {
+        self.top_right + (s * self.horizontal) - (t * self.vertical) - self.origin - offset
+    }
 }
 
 impl Default for Camera {
@@ -0,0 +1,148 @@
+/// Piecewise constant distribution over [0, 1], one bucket per function value
+#[derive(Debug, Clone)]
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    /// `func` holds non-negative values, all zeros give a uniform distribution
+    pub fn new(func: Vec<f64>) -> Self {
+        assert!(!func.is_empty(), "Empty distribution");
+        let n = func.len() as f64;
+        let mut cdf = Vec::with_capacity(func.len() + 1);
+        cdf.push(0.0);
+        for f in &func {
+            cdf.push(cdf.last().unwrap() + f / n);
+        }
+
+        let integral = *cdf.last().unwrap();
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0.0 {
+                *c / integral
+            } else {
+                i as f64 / n
+            };
+        }
+
+        Self {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    /// Integral of the function over [0, 1]
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    /// Maps `u` in [0, 1) to a sample in [0, 1), returning it with its density and bucket
+    pub fn sample(&self, u: f64) -> (f64, f64, usize) {
+        // Last bucket whose cdf starts at or before u
+        let i = (self.cdf.partition_point(|&c| c <= u).max(1) - 1).min(self.func.len() - 1);
+
+        let width = self.cdf[i + 1] - self.cdf[i];
+        let du = if width > 0.0 {
+            (u - self.cdf[i]) / width
+        } else {
+            0.0
+        };
+        let x = (i as f64 + du) / self.func.len() as f64;
+        (x.min(1.0 - f64::EPSILON), self.pdf_bucket(i), i)
+    }
+
+    /// Density of a sample in bucket `i`
+    pub fn pdf_bucket(&self, i: usize) -> f64 {
+        if self.integral > 0.0 {
+            self.func[i] / self.integral
+        } else {
+            1.0
+        }
+    }
+
+    /// Density at `x` in [0, 1]
+    pub fn pdf(&self, x: f64) -> f64 {
+        self.pdf_bucket(self.bucket(x))
+    }
+
+    fn bucket(&self, x: f64) -> usize {
+        ((x * self.func.len() as f64) as usize).min(self.func.len() - 1)
+    }
+}
+
+/// Piecewise constant distribution over [0, 1]^2 from a row major grid,
+/// picking a row first and then a column within it
+#[derive(Debug, Clone)]
+pub struct Distribution2D {
+    rows: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(func: &[f64], width: usize, height: usize) -> Self {
+        assert_eq!(func.len(), width * height, "Grid size mismatch");
+        let rows = func
+            .chunks(width)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect::<Vec<_>>();
+        let marginal = Distribution1D::new(rows.iter().map(|r| r.integral()).collect());
+        Self { rows, marginal }
+    }
+
+    /// Maps a point of [0, 1)^2 to a sample `(x, y)` with `y` selecting the row, and its density
+    pub fn sample(&self, u: (f64, f64)) -> ((f64, f64), f64) {
+        let (y, pdf_y, row) = self.marginal.sample(u.1);
+        let (x, pdf_x, _) = self.rows[row].sample(u.0);
+        ((x, y), pdf_x * pdf_y)
+    }
+
+    pub fn pdf(&self, x: f64, y: f64) -> f64 {
+        let row = self.marginal.bucket(y);
+        self.marginal.pdf_bucket(row) * self.rows[row].pdf(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distribution_1d() {
+        let d = Distribution1D::new(vec![1.0, 0.0, 3.0]);
+        assert_eq!(d.integral(), 4.0 / 3.0);
+
+        let (x, pdf, i) = d.sample(0.1);
+        assert_eq!(i, 0);
+        assert!((x - 0.4 / 3.0).abs() < 1e-12);
+        assert_eq!(pdf, 0.75);
+
+        // The empty bucket is never picked
+        let (x, pdf, i) = d.sample(0.25);
+        assert_eq!(i, 2);
+        assert!((x - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(pdf, 2.25);
+        assert_eq!(d.pdf(0.5), 0.0);
+
+        let uniform = Distribution1D::new(vec![0.0; 4]);
+        assert_eq!(uniform.sample(0.6).0, 0.6);
+        assert_eq!(uniform.pdf(0.6), 1.0);
+    }
+
+    #[test]
+    fn distribution_2d() {
+        let d = Distribution2D::new(&[1.0, 1.0, 0.0, 2.0], 2, 2);
+        let ((x, y), pdf) = d.sample((0.25, 0.9));
+        assert!(x >= 0.5 && y >= 0.5);
+        assert_eq!(pdf, d.pdf(x, y));
+        assert_eq!(d.pdf(0.25, 0.75), 0.0);
+
+        // The density integrates to one
+        let n = 64;
+        let sum: f64 = (0..n * n)
+            .map(|i| d.pdf((i % n) as f64 / n as f64, (i / n) as f64 / n as f64))
+            .sum();
+        assert!((sum / (n * n) as f64 - 1.0).abs() < 1e-12);
+    }
+}
@@ -1,5 +1,5 @@
 mod ray;
 mod vec3;
 
-pub use self::ray::Ray;
+pub use self::ray::{Ray, RayDiff};
 pub use self::vec3::Vec3;
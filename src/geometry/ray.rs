@@ -4,6 +4,17 @@ use super::Vec3;
 pub struct Ray {
     pub o: Vec3,
     pub d: Vec3,
+    /// Rays through the neighbouring pixels, when known
+    pub diff: Option<RayDiff>,
+}
+
+/// Origins and directions of the rays offset by one pixel in x and y
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub struct RayDiff {
+    pub rx_o: Vec3,
+    pub rx_d: Vec3,
+    pub ry_o: Vec3,
+    pub ry_d: Vec3,
 }
 
 impl Ray {
@@ -22,6 +33,7 @@ mod tests {
         let r = Ray {
             o: Vec3::splat(0.0),
             d: Vec3::splat(1.0),
+            diff: None,
         };
         assert_eq!(r.at(2.0), Vec3::splat(2.0))
     }
@@ -3,6 +3,7 @@ use std::ops::Index;
 use auto_ops::{impl_op_ex, impl_op_ex_commutative};
 
 use crate::rng::Rng;
+use crate::sampler::Sampler;
 
 #[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub struct Vec3 {
@@ -81,6 +82,11 @@ impl Vec3 {
         (self.x.abs() < ETA) && (self.y.abs() < ETA) && (self.z.abs() < ETA)
     }
 
+    /// Luminance of a linear Rec. 709 color
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+
     pub fn rotate_axis_angle(&self, axis: Self, angle: f64) -> Self {
         self * angle.cos()
             + Self::cross(axis, *self) * angle.sin()
@@ -108,22 +114,48 @@ impl Vec3 {
     }
 
     #[inline(always)]
-    pub fn random_unit_sphere(rng: &mut Rng) -> Self {
-        Self::random_range(rng, -1.0, 1.0).unit()
+    pub fn random_unit_sphere(sampler: &mut dyn Sampler) -> Self {
+        Self {
+            x: 2.0 * sampler.get_1d() - 1.0,
+            y: 2.0 * sampler.get_1d() - 1.0,
+            z: 2.0 * sampler.get_1d() - 1.0,
+        }
+        .unit()
     }
 
+    /// Uniformly distributed on the unit sphere
     #[inline(always)]
-    pub fn random_unit_disk(rng: &mut Rng) -> Self {
-        loop {
-            let p = Vec3 {
-                x: rng.range(-1.0, 1.0),
-                y: rng.range(-1.0, 1.0),
-                z: 0.0,
-            };
-            if p.len_sq() >= 1.0 {
-                continue;
-            }
-            return p;
+    pub fn random_unit_vector(sampler: &mut dyn Sampler) -> Self {
+        let (u, v) = sampler.get_2d();
+        let z = 1.0 - 2.0 * u;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * std::f64::consts::PI * v;
+        Self {
+            x: r * phi.cos(),
+            y: r * phi.sin(),
+            z,
+        }
+    }
+
+    /// Uniformly distributed in the unit disk around the z axis, mapping squares
+    /// to rings so evenly spread samples stay evenly spread (Shirley and Chiu)
+    #[inline(always)]
+    pub fn random_unit_disk(sampler: &mut dyn Sampler) -> Self {
+        let (u, v) = sampler.get_2d();
+        let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+        if a == 0.0 && b == 0.0 {
+            return Vec3::zero();
+        }
+        let quarter = std::f64::consts::FRAC_PI_4;
+        let (r, theta) = if a.abs() > b.abs() {
+            (a, quarter * (b / a))
+        } else {
+            (b, 2.0 * quarter - quarter * (a / b))
+        };
+        Vec3 {
+            x: r * theta.cos(),
+            y: r * theta.sin(),
+            z: 0.0,
         }
     }
 }
@@ -81,6 +81,7 @@ mod tests {
                 y: 0.0,
                 z: 0.0,
             },
+            diff: None,
         };
         let ray2 = Ray {
             o: Vec3 {
@@ -93,6 +94,7 @@ mod tests {
                 y: 0.1,
                 z: 0.1,
             },
+            diff: None,
         };
         assert_eq!(aabb.hit(ray, 0.0, 10.0), true);
         assert_eq!(aabb.hit(ray, 5.0, 10.0), false);
@@ -109,6 +111,7 @@ mod tests {
                 y: -5.6,
                 z: 7.5,
             },
+            diff: None,
         };
         let aabb2 = AABB {
             min: Vec3 {
@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use super::{HitRec, Hittable, Ray, AABB};
+use crate::rng;
+use crate::texture::Texture;
+
+/// How the alpha value of a hit is turned into a hit or a pass-through
+#[derive(Debug, Clone, Copy)]
+pub enum AlphaMode {
+    /// Hits with alpha below the threshold are ignored
+    Threshold(f64),
+    /// Hits are kept with probability equal to alpha
+    Stochastic,
+}
+
+/// Wraps a hittable and cuts it out wherever `alpha` (x channel) says so.
+/// Rays continue past cut out hits and may hit the object again further along.
+#[derive(Debug)]
+pub struct AlphaMask {
+    pub object: Box<dyn Hittable>,
+    pub alpha: Arc<dyn Texture>,
+    pub mode: AlphaMode,
+}
+
+impl AlphaMask {
+    fn keep(&self, alpha: f64, r: Ray, t: f64) -> bool {
+        match self.mode {
+            AlphaMode::Threshold(threshold) => alpha >= threshold,
+            AlphaMode::Stochastic => alpha > hash_unit(r, t),
+        }
+    }
+}
+
+impl Hittable for AlphaMask {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> HitRec<'_> {
+        let mut t_start = t_min;
+
+        loop {
+            match self.object.hit(r, t_start, t_max) {
+                HitRec::Hit(rec, mat) => {
+                    let alpha = self.alpha.value(rec.u, rec.v, rec.p).x;
+                    if self.keep(alpha, r, rec.t) {
+                        return HitRec::Hit(rec, mat);
+                    }
+                    t_start = rec.t + 1e-6;
+                }
+                HitRec::Miss => return HitRec::Miss,
+            }
+        }
+    }
+
+    fn aabb(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.object.aabb(t0, t1)
+    }
+}
+
+/// Deterministic value in [0, 1) for a point along a ray.
+/// `Hittable::hit` has no access to an `Rng` so stochastic alpha hashes the ray instead.
+fn hash_unit(r: Ray, t: f64) -> f64 {
+    let values = [r.o.x, r.o.y, r.o.z, r.d.x, r.d.y, r.d.z, t];
+    rng::hash_unit(&values.map(f64::to_bits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Vec3;
+    use crate::hittable::Sphere;
+    use crate::texture::SolidColor;
+
+    fn masked(alpha: f64, mode: AlphaMode) -> AlphaMask {
+        AlphaMask {
+            object: Box::new(Sphere {
+                c: Vec3::zero(),
+                r: 1.0,
+                mat: None,
+            }),
+            alpha: Arc::new(SolidColor {
+                color_value: Vec3::splat(alpha),
+            }),
+            mode,
+        }
+    }
+
+    #[test]
+    fn threshold() {
+        let r = Ray {
+            o: Vec3 {
+                x: -3.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            d: Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            diff: None,
+        };
+
+        assert!(matches!(
+            masked(1.0, AlphaMode::Threshold(0.5)).hit(r, 0.0, f64::INFINITY),
+            HitRec::Hit(_, _)
+        ));
+        assert!(matches!(
+            masked(0.2, AlphaMode::Threshold(0.5)).hit(r, 0.0, f64::INFINITY),
+            HitRec::Miss
+        ));
+    }
+
+    #[test]
+    fn stochastic() {
+        let mask = masked(0.5, AlphaMode::Stochastic);
+        let n = 1000;
+        let hits = (0..n)
+            .filter(|i| {
+                let r = Ray {
+                    o: Vec3 {
+                        x: -3.0,
+                        y: *i as f64 * 1e-4,
+                        z: 0.0,
+                    },
+                    d: Vec3 {
+                        x: 1.0,
+                        y: 0.0,
+                        z: 0.0,
+                    },
+                    diff: None,
+                };
+                // Either surface of the sphere may be kept
+                matches!(mask.hit(r, 0.0, 2.5), HitRec::Hit(_, _))
+            })
+            .count();
+        assert!(hits > 400 && hits < 600);
+    }
+}
@@ -158,6 +158,7 @@ mod tests {
                 y: 0.0,
                 z: 0.0,
             },
+            diff: None,
         };
 
         match bvh.hit(ray1, 0.0, f64::INFINITY) {
@@ -183,6 +184,7 @@ mod tests {
                 y: -1.0,
                 z: 0.0,
             },
+            diff: None,
         };
 
         match bvh.hit(ray2, 0.0, f64::INFINITY) {
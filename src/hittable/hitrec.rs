@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use crate::geometry::{Ray, Vec3};
 use crate::material::Material;
+use crate::texture::Footprint;
 
 #[derive(Clone, Copy)]
 pub enum HitRec<'mat> {
@@ -16,7 +17,17 @@ pub struct Rec {
     pub t: f64,
     pub u: f64,
     pub v: f64,
+    /// Partial derivatives of `p` along the surface parameterization.
+    /// Zero when the primitive doesn't provide them.
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    /// Area covered by the pixel this hit was traced from, see `compute_footprint`
+    pub footprint: Footprint,
     pub front_face: bool,
+    /// Index of the scene light this surface is, when it's sampled as an area light
+    pub light: Option<usize>,
+    /// Id of the object hit, for light linking
+    pub object: Option<usize>,
 }
 
 impl<'mat> HitRec<'mat> {
@@ -44,9 +55,175 @@ impl<'mat> HitRec<'mat> {
                 t,
                 u,
                 v,
+                dpdu: Vec3::zero(),
+                dpdv: Vec3::zero(),
+                footprint: Footprint::default(),
                 front_face,
+                light: None,
+                object: None,
             },
             mat,
         )
     }
+
+    /// Attaches the surface tangents to a hit
+    pub fn with_tangents(self, dpdu: Vec3, dpdv: Vec3) -> Self {
+        match self {
+            Self::Hit(rec, mat) => Self::Hit(Rec { dpdu, dpdv, ..rec }, mat),
+            Self::Miss => Self::Miss,
+        }
+    }
+
+    /// Marks a hit as landing on the object with id `id`
+    pub fn with_object(self, id: usize) -> Self {
+        match self {
+            Self::Hit(rec, mat) => Self::Hit(
+                Rec {
+                    object: Some(id),
+                    ..rec
+                },
+                mat,
+            ),
+            Self::Miss => Self::Miss,
+        }
+    }
+
+    /// Marks a hit as landing on the scene light at `index`
+    pub fn with_light(self, index: usize) -> Self {
+        match self {
+            Self::Hit(rec, mat) => Self::Hit(
+                Rec {
+                    light: Some(index),
+                    ..rec
+                },
+                mat,
+            ),
+            Self::Miss => Self::Miss,
+        }
+    }
+}
+
+impl Rec {
+    /// Fills in `footprint` by intersecting the differentials of `r` with the tangent plane
+    pub fn compute_footprint(&mut self, r: Ray) {
+        let diff = match r.diff {
+            Some(diff) => diff,
+            None => return,
+        };
+
+        let dx = Vec3::dot(self.n, diff.rx_d);
+        let dy = Vec3::dot(self.n, diff.ry_d);
+        if dx.abs() < 1e-12 || dy.abs() < 1e-12 {
+            return;
+        }
+        let tx = Vec3::dot(self.n, self.p - diff.rx_o) / dx;
+        let ty = Vec3::dot(self.n, self.p - diff.ry_o) / dy;
+        let dpdx = diff.rx_o + tx * diff.rx_d - self.p;
+        let dpdy = diff.ry_o + ty * diff.ry_d - self.p;
+
+        // Least squares fit of dp = du * dpdu + dv * dpdv
+        let a00 = Vec3::dot(self.dpdu, self.dpdu);
+        let a01 = Vec3::dot(self.dpdu, self.dpdv);
+        let a11 = Vec3::dot(self.dpdv, self.dpdv);
+        let det = a00 * a11 - a01 * a01;
+        let solve = |dp: Vec3| {
+            if det.abs() < 1e-12 {
+                return (0.0, 0.0);
+            }
+            let b0 = Vec3::dot(self.dpdu, dp);
+            let b1 = Vec3::dot(self.dpdv, dp);
+            ((a11 * b0 - a01 * b1) / det, (a00 * b1 - a01 * b0) / det)
+        };
+        let (dudx, dvdx) = solve(dpdx);
+        let (dudy, dvdy) = solve(dpdy);
+
+        self.footprint = Footprint {
+            width: dpdx.len().max(dpdy.len()),
+            uv_width: dudx.abs().max(dvdx.abs()).max(dudy.abs()).max(dvdy.abs()),
+        };
+    }
+
+    /// Orthonormal shading frame (tangent, bitangent, normal) around `n`.
+    /// Falls back to an arbitrary tangent when no `dpdu` is available.
+    pub fn frame(&self) -> (Vec3, Vec3, Vec3) {
+        let n = self.n;
+        let mut t = self.dpdu - Vec3::dot(self.dpdu, n) * n;
+        if t.near_zero() {
+            let a = if n.x.abs() > 0.9 {
+                Vec3 {
+                    x: 0.0,
+                    y: 1.0,
+                    z: 0.0,
+                }
+            } else {
+                Vec3 {
+                    x: 1.0,
+                    y: 0.0,
+                    z: 0.0,
+                }
+            };
+            t = Vec3::cross(a, n);
+        }
+        let t = t.unit();
+        (t, Vec3::cross(n, t), n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::RayDiff;
+
+    #[test]
+    fn compute_footprint() {
+        let mut rec = Rec {
+            n: Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: 1.0,
+            },
+            dpdu: Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            dpdv: Vec3 {
+                x: 0.0,
+                y: 2.0,
+                z: 0.0,
+            },
+            ..Rec::default()
+        };
+        let o = Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        };
+        let r = Ray {
+            o,
+            d: Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: -1.0,
+            },
+            diff: Some(RayDiff {
+                rx_o: o,
+                rx_d: Vec3 {
+                    x: 0.01,
+                    y: 0.0,
+                    z: -1.0,
+                },
+                ry_o: o,
+                ry_d: Vec3 {
+                    x: 0.0,
+                    y: 0.01,
+                    z: -1.0,
+                },
+            }),
+        };
+
+        rec.compute_footprint(r);
+        assert!((rec.footprint.width - 0.01).abs() < 1e-12);
+        assert!((rec.footprint.uv_width - 0.01).abs() < 1e-12);
+    }
 }
@@ -1,23 +1,64 @@
 mod aabb;
+mod alpha;
 mod bvh;
 mod hitrec;
 mod sphere;
+mod tagged;
+mod triangle;
 
 use std::fmt::Debug;
+use std::sync::Arc;
 
 use crate::geometry::{Ray, Vec3};
+use crate::material::Material;
 use crate::rng::Rng;
+use crate::sampler::Sampler;
 
 pub use aabb::AABB;
+pub use alpha::{AlphaMask, AlphaMode};
 pub use bvh::BVH;
 pub use hitrec::{HitRec, Rec};
 pub use sphere::Sphere;
+pub use tagged::Tagged;
+pub use triangle::Triangle;
 
 pub trait Hittable: Send + Sync + Debug {
     fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> HitRec;
     fn aabb(&self, t0: f64, t1: f64) -> Option<AABB>;
 }
 
+/// A surface that can be sampled, so emissive ones can light the scene as area lights
+pub trait Shape: Hittable {
+    fn area(&self) -> f64;
+
+    fn material(&self) -> Option<&Arc<dyn Material>>;
+
+    /// Point for `u` in [0, 1)^2, spread evenly over the surface, facing outwards
+    fn surface_point(&self, u: (f64, f64)) -> Rec;
+
+    /// The `u` that `surface_point` maps to the point `p` of the surface
+    fn surface_coords(&self, p: Vec3) -> (f64, f64);
+
+    /// Picks a point of the surface seen from `p`, returning the hit towards it
+    /// and the solid angle density of that direction
+    fn sample(&self, p: Vec3, sampler: &mut dyn Sampler) -> Option<(Rec, f64)>;
+
+    /// Solid angle density `sample` picks the unit direction `wi` with from `p`
+    fn pdf(&self, p: Vec3, wi: Vec3) -> f64;
+
+    /// Axis and cosine of the half angle of a cone holding every outward normal
+    fn normal_cone(&self) -> (Vec3, f64);
+}
+
+/// Solid angle density of a point found at `rec` when picked by area, seen from `p`
+pub(crate) fn area_to_solid_angle(p: Vec3, rec: &Rec, area: f64) -> f64 {
+    let cos = Vec3::dot(rec.n, (rec.p - p).unit()).abs();
+    if cos <= 0.0 {
+        return 0.0;
+    }
+    (rec.p - p).len_sq() / (cos * area)
+}
+
 #[derive(Default, Debug)]
 pub struct HittableList {
     pub objects: Vec<Box<dyn Hittable>>,
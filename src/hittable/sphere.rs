@@ -1,8 +1,9 @@
 use std::f64::consts::PI;
 use std::sync::Arc;
 
-use super::{HitRec, Hittable, Ray, Vec3, AABB};
+use super::{area_to_solid_angle, HitRec, Hittable, Ray, Rec, Shape, Vec3, AABB};
 use crate::material::Material;
+use crate::sampler::Sampler;
 
 #[derive(Default, Debug)]
 pub struct Sphere {
@@ -13,11 +14,32 @@ pub struct Sphere {
 
 impl Sphere {
     fn uv(p: Vec3) -> (f64, f64) {
-        let theta = -p.y.acos();
+        let theta = (-p.y).acos();
         let phi = -p.z.atan2(p.x) + PI;
 
         (phi / (2.0 * PI), theta / PI)
     }
+
+    /// dp/du and dp/dv for the parameterization used by `uv`, `n` being the unit normal
+    fn tangents(&self, n: Vec3) -> (Vec3, Vec3) {
+        let sin_theta = (n.x * n.x + n.z * n.z).sqrt().max(1e-8);
+        let dpdu = 2.0
+            * PI
+            * self.r
+            * Vec3 {
+                x: n.z,
+                y: 0.0,
+                z: -n.x,
+            };
+        let dpdv = PI
+            * self.r
+            * Vec3 {
+                x: -n.x * n.y / sin_theta,
+                y: sin_theta,
+                z: -n.y * n.z / sin_theta,
+            };
+        (dpdu, dpdv)
+    }
 }
 
 impl Hittable for Sphere {
@@ -46,10 +68,11 @@ impl Hittable for Sphere {
         let p = r.at(root);
         let n = (p - self.c) / self.r;
         let (u, v) = Self::uv(n);
+        let (dpdu, dpdv) = self.tangents(n);
         if let Some(ref mat) = self.mat {
-            HitRec::hit(p, t, u, v, r, n, Some(mat))
+            HitRec::hit(p, t, u, v, r, n, Some(mat)).with_tangents(dpdu, dpdv)
         } else {
-            HitRec::hit(p, t, u, v, r, n, None)
+            HitRec::hit(p, t, u, v, r, n, None).with_tangents(dpdu, dpdv)
         }
     }
 
@@ -61,6 +84,135 @@ impl Hittable for Sphere {
     }
 }
 
+impl Sphere {
+    /// Hit of the ray from `p` towards `wi`, if any
+    fn hit_from(&self, p: Vec3, wi: Vec3) -> Option<Rec> {
+        let r = Ray {
+            o: p,
+            d: wi,
+            diff: None,
+        };
+        match self.hit(r, 1e-6, f64::INFINITY) {
+            HitRec::Hit(rec, _) => Some(rec),
+            HitRec::Miss => None,
+        }
+    }
+
+    /// One minus the cosine of the half angle the sphere covers seen from `p`,
+    /// `None` when `p` is inside
+    fn cone_from(&self, p: Vec3) -> Option<f64> {
+        let dist_sq = (self.c - p).len_sq();
+        let sin_sq = self.r * self.r / dist_sq;
+        if sin_sq >= 1.0 {
+            return None;
+        }
+        // Stays precise for spheres that are small or far away
+        Some(sin_sq / (1.0 + (1.0 - sin_sq).sqrt()))
+    }
+}
+
+impl Shape for Sphere {
+    fn area(&self) -> f64 {
+        4.0 * PI * self.r * self.r
+    }
+
+    fn material(&self) -> Option<&Arc<dyn Material>> {
+        self.mat.as_ref()
+    }
+
+    fn surface_point(&self, u: (f64, f64)) -> Rec {
+        let z = 1.0 - 2.0 * u.0;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * u.1;
+        let n = Vec3 {
+            x: r * phi.cos(),
+            y: r * phi.sin(),
+            z,
+        };
+        let (u, v) = Self::uv(n);
+        let (dpdu, dpdv) = self.tangents(n);
+        Rec {
+            p: self.c + self.r * n,
+            n,
+            u,
+            v,
+            dpdu,
+            dpdv,
+            front_face: true,
+            ..Rec::default()
+        }
+    }
+
+    fn surface_coords(&self, p: Vec3) -> (f64, f64) {
+        let n = (p - self.c).unit();
+        let phi = n.y.atan2(n.x);
+        let phi = if phi < 0.0 { phi + 2.0 * PI } else { phi };
+        (((1.0 - n.z) / 2.0).clamp(0.0, 1.0), phi / (2.0 * PI))
+    }
+
+    fn sample(&self, p: Vec3, sampler: &mut dyn Sampler) -> Option<(Rec, f64)> {
+        let one_minus_cos = match self.cone_from(p) {
+            Some(c) => c,
+            None => {
+                // From inside every point is visible, pick by area
+                let q = self.surface_point(sampler.get_2d()).p;
+                let rec = self.hit_from(p, (q - p).unit())?;
+                let pdf = area_to_solid_angle(p, &rec, self.area());
+                return Some((rec, pdf));
+            }
+        };
+
+        // Uniform over the cone of directions towards the sphere
+        let (u1, u2) = sampler.get_2d();
+        let cos_theta = 1.0 - u1 * one_minus_cos;
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u2;
+
+        let w = (self.c - p).unit();
+        let a = if w.x.abs() > 0.9 {
+            Vec3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            }
+        } else {
+            Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            }
+        };
+        let u = Vec3::cross(a, w).unit();
+        let v = Vec3::cross(w, u);
+        let wi = (sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * w).unit();
+
+        let rec = self.hit_from(p, wi)?;
+        Some((rec, 1.0 / (2.0 * PI * one_minus_cos)))
+    }
+
+    fn pdf(&self, p: Vec3, wi: Vec3) -> f64 {
+        let rec = match self.hit_from(p, wi) {
+            Some(rec) => rec,
+            None => return 0.0,
+        };
+        match self.cone_from(p) {
+            Some(one_minus_cos) => 1.0 / (2.0 * PI * one_minus_cos),
+            None => area_to_solid_angle(p, &rec, self.area()),
+        }
+    }
+
+    fn normal_cone(&self) -> (Vec3, f64) {
+        (
+            Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: 1.0,
+            },
+            -1.0,
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -75,10 +227,12 @@ mod tests {
         let r1 = Ray {
             o: Vec3::splat(2.0),
             d: Vec3::splat(1.0),
+            diff: None,
         };
         let r2 = Ray {
             o: Vec3::splat(-2.0),
             d: Vec3::splat(1.0),
+            diff: None,
         };
 
         assert!(match s.hit(r1, 0.0, 10.0) {
@@ -91,6 +245,77 @@ mod tests {
         });
     }
 
+    #[test]
+    fn uv() {
+        let pole = |y: f64| Sphere::uv(Vec3 { x: 0.0, y, z: 0.0 }).1;
+        assert_eq!(pole(-1.0), 0.0);
+        assert_eq!(pole(1.0), 1.0);
+        let (u, v) = Sphere::uv(Vec3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        });
+        assert!((u - 0.5).abs() < 1e-12 && (v - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn tangents() {
+        let s = Sphere {
+            c: Vec3::splat(1.0),
+            r: 2.0,
+            mat: None,
+        };
+        let r = Ray {
+            o: Vec3 {
+                x: -4.0,
+                y: 1.5,
+                z: 1.3,
+            },
+            d: Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            diff: None,
+        };
+
+        match s.hit(r, 0.0, 10.0) {
+            HitRec::Hit(rec, _) => {
+                let outward = (rec.p - s.c).unit();
+                assert!(Vec3::dot(rec.dpdu, outward).abs() < 1e-9);
+                assert!(Vec3::dot(rec.dpdv, outward).abs() < 1e-9);
+                assert!(Vec3::dot(Vec3::cross(rec.dpdu, rec.dpdv).unit(), outward) > 0.999);
+
+                // dp/du matches a finite difference of the parameterization
+                let du = 1e-6;
+                let (u, v) = Sphere::uv(outward);
+                let phi = 2.0 * PI * (u + du);
+                let theta = PI * v;
+                let shifted = Vec3 {
+                    x: -theta.sin() * phi.cos(),
+                    y: -theta.cos(),
+                    z: theta.sin() * phi.sin(),
+                };
+                let fd = (s.c + s.r * shifted - rec.p) / du;
+                assert!((fd - rec.dpdu).len() < 1e-4);
+            }
+            HitRec::Miss => panic!("expected a hit"),
+        }
+    }
+
+    #[test]
+    fn surface_coords() {
+        let s = Sphere {
+            c: Vec3::splat(1.0),
+            r: 2.0,
+            mat: None,
+        };
+        for &u in [(0.3, 0.8), (0.9, 0.1), (0.5, 0.5)].iter() {
+            let (a, b) = s.surface_coords(s.surface_point(u).p);
+            assert!((a - u.0).abs() < 1e-9 && (b - u.1).abs() < 1e-9);
+        }
+    }
+
     #[test]
     fn aabb() {
         let s = Sphere {
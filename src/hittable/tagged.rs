@@ -0,0 +1,54 @@
+use super::{HitRec, Hittable, Ray, AABB};
+
+/// Wraps a hittable and marks its hits with `id`, so lights can be linked to it
+#[derive(Debug)]
+pub struct Tagged {
+    pub object: Box<dyn Hittable>,
+    pub id: usize,
+}
+
+impl Hittable for Tagged {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> HitRec<'_> {
+        self.object.hit(r, t_min, t_max).with_object(self.id)
+    }
+
+    fn aabb(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.object.aabb(t0, t1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Vec3;
+    use crate::hittable::Sphere;
+
+    #[test]
+    fn tags_hits() {
+        let tagged = Tagged {
+            object: Box::new(Sphere {
+                c: Vec3::zero(),
+                r: 1.0,
+                mat: None,
+            }),
+            id: 3,
+        };
+        let r = Ray {
+            o: Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: 5.0,
+            },
+            d: Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: -1.0,
+            },
+            diff: None,
+        };
+        match tagged.hit(r, 0.0, f64::INFINITY) {
+            HitRec::Hit(rec, _) => assert_eq!(rec.object, Some(3)),
+            HitRec::Miss => panic!("missed the sphere"),
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use std::sync::Arc;
+
+use super::{area_to_solid_angle, HitRec, Hittable, Ray, Rec, Shape, Vec3, AABB};
+use crate::material::Material;
+use crate::sampler::Sampler;
+
+/// A single triangle, facing the side its vertices wind counterclockwise around
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    pub p: [Vec3; 3],
+    /// Texture coordinates of each vertex
+    pub uv: [(f64, f64); 3],
+    pub mat: Option<Arc<dyn Material>>,
+}
+
+impl Triangle {
+    /// A triangle with texture coordinates (0, 0), (1, 0) and (0, 1)
+    pub fn new(p: [Vec3; 3], mat: Option<Arc<dyn Material>>) -> Self {
+        Self {
+            p,
+            uv: [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+            mat,
+        }
+    }
+
+    /// The parallelogram spanned by `edge_u` and `edge_v` from `corner`, as a grid
+    /// of `divisions` by `divisions` cells of two triangles each. Texture
+    /// coordinates run from 0 to 1 along the edges and it faces `edge_u × edge_v`.
+    pub fn quad(
+        corner: Vec3,
+        edge_u: Vec3,
+        edge_v: Vec3,
+        divisions: usize,
+        mat: Option<Arc<dyn Material>>,
+    ) -> Vec<Self> {
+        let n = divisions.max(1);
+        let point = |i: usize, j: usize| {
+            let (u, v) = (i as f64 / n as f64, j as f64 / n as f64);
+            (corner + u * edge_u + v * edge_v, (u, v))
+        };
+
+        let mut triangles = Vec::with_capacity(2 * n * n);
+        for j in 0..n {
+            for i in 0..n {
+                let corners = [
+                    point(i, j),
+                    point(i + 1, j),
+                    point(i + 1, j + 1),
+                    point(i, j + 1),
+                ];
+                for &[a, b, c] in [[0, 1, 2], [0, 2, 3]].iter() {
+                    triangles.push(Self {
+                        p: [corners[a].0, corners[b].0, corners[c].0],
+                        uv: [corners[a].1, corners[b].1, corners[c].1],
+                        mat: mat.clone(),
+                    });
+                }
+            }
+        }
+        triangles
+    }
+
+    fn normal(&self) -> Vec3 {
+        Vec3::cross(self.p[1] - self.p[0], self.p[2] - self.p[0]).unit()
+    }
+
+    /// Position, texture coordinates and tangents at barycentric coordinates `b1`, `b2`
+    fn interpolate(&self, b1: f64, b2: f64) -> (Vec3, (f64, f64), Vec3, Vec3) {
+        let b0 = 1.0 - b1 - b2;
+        let p = b0 * self.p[0] + b1 * self.p[1] + b2 * self.p[2];
+        let uv = (
+            b0 * self.uv[0].0 + b1 * self.uv[1].0 + b2 * self.uv[2].0,
+            b0 * self.uv[0].1 + b1 * self.uv[1].1 + b2 * self.uv[2].1,
+        );
+
+        let e1 = self.p[1] - self.p[0];
+        let e2 = self.p[2] - self.p[0];
+        let (du1, dv1) = (self.uv[1].0 - self.uv[0].0, self.uv[1].1 - self.uv[0].1);
+        let (du2, dv2) = (self.uv[2].0 - self.uv[0].0, self.uv[2].1 - self.uv[0].1);
+        let det = du1 * dv2 - dv1 * du2;
+        let (dpdu, dpdv) = if det.abs() < 1e-12 {
+            (Vec3::zero(), Vec3::zero())
+        } else {
+            ((dv2 * e1 - dv1 * e2) / det, (du1 * e2 - du2 * e1) / det)
+        };
+        (p, uv, dpdu, dpdv)
+    }
+
+    fn hit_from(&self, p: Vec3, wi: Vec3) -> Option<Rec> {
+        let r = Ray {
+            o: p,
+            d: wi,
+            diff: None,
+        };
+        match self.hit(r, 1e-6, f64::INFINITY) {
+            HitRec::Hit(rec, _) => Some(rec),
+            HitRec::Miss => None,
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> HitRec<'_> {
+        // Möller-Trumbore
+        let e1 = self.p[1] - self.p[0];
+        let e2 = self.p[2] - self.p[0];
+        let pvec = Vec3::cross(r.d, e2);
+        let det = Vec3::dot(e1, pvec);
+        if det.abs() < 1e-12 {
+            return HitRec::Miss;
+        }
+        let inv_det = 1.0 / det;
+
+        let tvec = r.o - self.p[0];
+        let b1 = Vec3::dot(tvec, pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return HitRec::Miss;
+        }
+        let qvec = Vec3::cross(tvec, e1);
+        let b2 = Vec3::dot(r.d, qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return HitRec::Miss;
+        }
+        let t = Vec3::dot(e2, qvec) * inv_det;
+        if t < t_min || t_max < t {
+            return HitRec::Miss;
+        }
+
+        let (_, (u, v), dpdu, dpdv) = self.interpolate(b1, b2);
+        HitRec::hit(r.at(t), t, u, v, r, self.normal(), self.mat.as_ref()).with_tangents(dpdu, dpdv)
+    }
+
+    fn aabb(&self, _t0: f64, _t1: f64) -> Option<AABB> {
+        // Padded so triangles lying in an axis plane still have a volume
+        let pad = Vec3::splat(1e-4);
+        let bounds = AABB::grow(
+            AABB {
+                min: self.p[0],
+                max: self.p[0],
+            },
+            AABB::grow(
+                AABB {
+                    min: self.p[1],
+                    max: self.p[1],
+                },
+                AABB {
+                    min: self.p[2],
+                    max: self.p[2],
+                },
+            ),
+        );
+        Some(AABB {
+            min: bounds.min - pad,
+            max: bounds.max + pad,
+        })
+    }
+}
+
+impl Shape for Triangle {
+    fn area(&self) -> f64 {
+        0.5 * Vec3::cross(self.p[1] - self.p[0], self.p[2] - self.p[0]).len()
+    }
+
+    fn material(&self) -> Option<&Arc<dyn Material>> {
+        self.mat.as_ref()
+    }
+
+    fn surface_point(&self, u: (f64, f64)) -> Rec {
+        let su = u.0.sqrt();
+        let (p, (u, v), dpdu, dpdv) = self.interpolate(su * (1.0 - u.1), su * u.1);
+        Rec {
+            p,
+            n: self.normal(),
+            u,
+            v,
+            dpdu,
+            dpdv,
+            front_face: true,
+            ..Rec::default()
+        }
+    }
+
+    fn surface_coords(&self, p: Vec3) -> (f64, f64) {
+        // Barycentric coordinates of p, then undo the square root warp
+        let (e1, e2, d) = (self.p[1] - self.p[0], self.p[2] - self.p[0], p - self.p[0]);
+        let (d11, d12, d22) = (e1.len_sq(), Vec3::dot(e1, e2), e2.len_sq());
+        let (d1, d2) = (Vec3::dot(d, e1), Vec3::dot(d, e2));
+        let det = d11 * d22 - d12 * d12;
+        if det.abs() < 1e-24 {
+            return (0.0, 0.0);
+        }
+        let b1 = (d22 * d1 - d12 * d2) / det;
+        let b2 = (d11 * d2 - d12 * d1) / det;
+        let su = (b1 + b2).clamp(0.0, 1.0);
+        let v = if su > 0.0 { b2 / su } else { 0.0 };
+        (su * su, v.clamp(0.0, 1.0))
+    }
+
+    fn sample(&self, p: Vec3, sampler: &mut dyn Sampler) -> Option<(Rec, f64)> {
+        let q = self.surface_point(sampler.get_2d()).p;
+        let rec = self.hit_from(p, (q - p).unit())?;
+        let pdf = area_to_solid_angle(p, &rec, self.area());
+        if pdf > 0.0 {
+            Some((rec, pdf))
+        } else {
+            None
+        }
+    }
+
+    fn pdf(&self, p: Vec3, wi: Vec3) -> f64 {
+        match self.hit_from(p, wi) {
+            Some(rec) => area_to_solid_angle(p, &rec, self.area()),
+            None => 0.0,
+        }
+    }
+
+    fn normal_cone(&self) -> (Vec3, f64) {
+        (self.normal(), 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    #[test]
+    fn hit() {
+        let quad = Triangle::quad(
+            Vec3::zero(),
+            Vec3 {
+                x: 2.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            Vec3 {
+                x: 0.0,
+                y: 2.0,
+                z: 0.0,
+            },
+            2,
+            None,
+        );
+        assert_eq!(quad.len(), 8);
+        assert!((quad.iter().map(|t| t.area()).sum::<f64>() - 4.0).abs() < 1e-12);
+
+        let r = Ray {
+            o: Vec3 {
+                x: 1.5,
+                y: 0.25,
+                z: 1.0,
+            },
+            d: Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: -1.0,
+            },
+            diff: None,
+        };
+        let hits = quad
+            .iter()
+            .filter_map(|t| match t.hit(r, 0.0, 10.0) {
+                HitRec::Hit(rec, _) => Some(rec),
+                HitRec::Miss => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(hits.len(), 1);
+        let rec = hits[0];
+        assert!((rec.t - 1.0).abs() < 1e-12);
+        assert!((rec.u - 0.75).abs() < 1e-12 && (rec.v - 0.125).abs() < 1e-12);
+        assert!(rec.front_face);
+        assert!((rec.dpdu.x - 2.0).abs() < 1e-12 && (rec.dpdv.y - 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn sampling() {
+        let t = Triangle::new(
+            [
+                Vec3::zero(),
+                Vec3 {
+                    x: 1.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                Vec3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: -1.0,
+                },
+            ],
+            None,
+        );
+        let p = Vec3 {
+            x: 0.2,
+            y: 1.0,
+            z: -0.2,
+        };
+        let mut rng = Rng::new(5);
+
+        let (u, v) = t.surface_coords(t.surface_point((0.3, 0.8)).p);
+        assert!((u - 0.3).abs() < 1e-9 && (v - 0.8).abs() < 1e-9);
+
+        // Averaging 1 / pdf estimates the solid angle the triangle covers
+        let n = 20000;
+        let mut solid_angle = 0.0;
+        for _ in 0..n {
+            let (rec, pdf) = t.sample(p, &mut rng).unwrap();
+            assert!((pdf - t.pdf(p, (rec.p - p).unit())).abs() < 1e-6 * pdf);
+            solid_angle += 1.0 / pdf;
+        }
+        solid_angle /= n as f64;
+
+        // Van Oosterom and Strackee
+        let a = t.p[0] - p;
+        let b = t.p[1] - p;
+        let c = t.p[2] - p;
+        let (la, lb, lc) = (a.len(), b.len(), c.len());
+        let exact = 2.0
+            * Vec3::dot(a, Vec3::cross(b, c)).abs().atan2(
+                la * lb * lc + Vec3::dot(a, b) * lc + Vec3::dot(a, c) * lb + Vec3::dot(b, c) * la,
+            );
+        assert!((solid_angle - exact).abs() < 0.02 * exact);
+    }
+}
@@ -1,11 +1,19 @@
-mod camera;
-mod geometry;
-mod hittable;
-mod material;
-mod perlin;
-mod rng;
+//! The modules a `SceneTrait` needs to build a `Scene` outside of this crate
+//! are public, the ones only the renderer uses are not.
+
+pub mod background;
+pub mod camera;
+mod distribution;
+pub mod geometry;
+pub mod hittable;
+pub mod light;
+pub mod material;
+pub mod perlin;
+pub mod rng;
+pub mod sampler;
 mod scene;
-mod texture;
+mod spectrum;
+pub mod texture;
 
 use std::fs::File;
 use std::io::BufWriter;
@@ -15,16 +23,38 @@ use indicatif::{ParallelProgressIterator, ProgressBar};
 use rayon::prelude::*;
 
 use geometry::{Ray, Vec3};
-use hittable::{HitRec, Hittable, HittableList};
+use hittable::{HitRec, Hittable, Rec};
+use light::LightTree;
+use material::Material;
 use rng::Rng;
+use sampler::{IndependentSampler, Sampler};
 pub use scene::*;
 
+/// Longest path traced, counting the camera ray
+const MAX_DEPTH: usize = 50;
+
 /// Holds info about an image. Handles rendering.
 pub struct Renderer {
     pub width: usize,
     pub height: usize,
+    /// Trace a single wavelength per sample instead of RGB triples
+    pub spectral: bool,
+    /// Draws the pixel, lens, wavelength, light and material samples of each path
+    pub sampler: Box<dyn Sampler>,
+    /// Keep sampling noisy pixels past the count given to `render`
+    pub adaptive: Option<AdaptiveSampling>,
     scene: Scene,
+    /// Picks among the scene's lights, built along with the scene
+    lights: LightTree,
     image: Option<Vec<u8>>,
+    /// One image per light group of the scene
+    group_images: Vec<Vec<u8>>,
+    /// Samples taken in each pixel, row by row
+    sample_counts: Vec<usize>,
+    /// Sums of every pixel's samples, kept between progressive passes
+    pixels: Vec<Pixel>,
+    /// Width and height `pixels` was made for
+    pixels_size: (usize, usize),
 }
 
 impl Renderer {
@@ -32,14 +62,46 @@ impl Renderer {
         Self {
             width,
             height: width,
+            spectral: false,
+            sampler: Box::new(IndependentSampler::new(0)),
+            adaptive: None,
             scene: Scene::default(),
+            lights: LightTree::default(),
             image: None,
+            group_images: Vec::new(),
+            sample_counts: Vec::new(),
+            pixels: Vec::new(),
+            pixels_size: (0, 0),
         }
     }
 
     pub fn render(&mut self, n_samples: usize) {
-        let max_depth = 50;
+        self.pixels_size = (0, 0);
+        // Adaptive sampling comes back for the pixels that are still noisy
+        while self.render_pass(n_samples) > 0 && self.adaptive.is_some() {}
+        self.resolve();
+    }
+
+    /// Renders `passes` passes of `pass_samples` samples per pixel, calling
+    /// `on_pass` with the number of passes done after each one, when the image
+    /// and `estimate` hold everything sampled so far. Samples add up with those
+    /// of earlier calls for the same scene, so rendering can be resumed.
+    pub fn render_progressive<F: FnMut(&Self, usize)>(
+        &mut self,
+        pass_samples: usize,
+        passes: usize,
+        mut on_pass: F,
+    ) {
+        for pass in 0..passes {
+            self.render_pass(pass_samples);
+            self.resolve();
+            on_pass(self, pass + 1);
+        }
+    }
 
+    /// Adds up to `n_samples` samples to every pixel adaptive sampling doesn't
+    /// consider done, returning how many samples were taken
+    fn render_pass(&mut self, n_samples: usize) -> usize {
         let prog_bar = ProgressBar::new(self.height as u64);
         prog_bar.set_style(
             indicatif::ProgressStyle::default_bar()
@@ -50,67 +112,162 @@ impl Renderer {
         );
         prog_bar.set_message("Rendering");
 
-        let img_buf: Vec<u8> = (0..self.height)
-            .into_par_iter()
+        let n_groups = self.scene.light_groups.len();
+        let mut pixels = std::mem::take(&mut self.pixels);
+        if self.pixels_size != (self.width, self.height) {
+            pixels = vec![Pixel::new(n_groups); self.width * self.height];
+            self.pixels_size = (self.width, self.height);
+        }
+
+        let taken = pixels
+            .par_chunks_mut(self.width)
+            .enumerate()
             .progress_with(prog_bar)
-            .map(|j| {
-                let mut row_buf: Vec<u8> = Vec::with_capacity(self.width * 3);
-                let mut rng = Rng::new(123 + j as u32);
-                for i in 0..self.width {
-                    let mut pixel_color = Vec3::zero();
-                    for _n in 0..n_samples {
-                        let u = (i as f64 + rng.gen()) / ((self.width - 1) as f64);
-                        let v = (j as f64 + rng.gen()) / ((self.height - 1) as f64);
-
-                        let r = self.scene.camera.get_ray(u, v, &mut rng);
-                        pixel_color += Self::ray_color(
-                            r,
-                            &self.scene.background,
-                            &self.scene.world,
-                            &mut rng,
-                            max_depth,
+            .map(|(j, row)| {
+                let mut sampler = self.sampler.clone_box();
+                let mut taken = 0;
+                for (i, pixel) in row.iter_mut().enumerate() {
+                    if !self.wants_samples(pixel, n_samples) {
+                        continue;
+                    }
+                    let batch = match self.adaptive {
+                        Some(a) => n_samples.min(a.max_samples(n_samples) - pixel.stats.n),
+                        None => n_samples,
+                    };
+                    // Each batch of `n_samples` is spread evenly by the sampler on its own
+                    for _ in 0..batch {
+                        let color = self.pixel_sample(
+                            sampler.as_mut(),
+                            (i, j),
+                            pixel.stats.n,
+                            n_samples,
+                            &mut pixel.groups,
                         );
+                        pixel.color += color;
+                        pixel.stats.push(self.luminance(color));
                     }
-                    Self::write_color(&mut row_buf, pixel_color, n_samples);
+                    taken += batch;
                 }
-                row_buf
+                taken
             })
-            .flatten()
-            .collect();
+            .sum();
 
-        self.image = Some(img_buf);
+        self.pixels = pixels;
+        taken
     }
 
-    fn ray_color(
-        r: Ray,
-        background: &Vec3,
-        world: &HittableList,
-        rng: &mut Rng,
-        depth: usize,
-    ) -> Vec3 {
-        if depth <= 0 {
-            return Vec3::zero();
+    /// Whether sampling `pixel` more is worth it, in passes of `n_samples`
+    fn wants_samples(&self, pixel: &Pixel, n_samples: usize) -> bool {
+        match self.adaptive {
+            Some(a) => pixel.stats.n < a.max_samples(n_samples) && a.noisy(&pixel.stats),
+            None => true,
         }
+    }
 
-        match world.hit(r, 0.001, f64::INFINITY) {
-            HitRec::Hit(rec, mat) => match mat {
-                Some(mat) => {
-                    let emitted = mat.emitted(rec.u, rec.v, rec.p);
+    /// Turns the sums in the accumulation buffer into images
+    fn resolve(&mut self) {
+        let n_groups = self.scene.light_groups.len();
+        let mut image = Vec::with_capacity(self.pixels.len() * 3);
+        let mut group_images = vec![Vec::with_capacity(self.pixels.len() * 3); n_groups];
+        for pixel in self.pixels.iter() {
+            Self::write_color(&mut image, pixel.color, pixel.stats.n, self.spectral);
+            for (group_image, &color) in group_images.iter_mut().zip(&pixel.groups) {
+                Self::write_color(group_image, color, pixel.stats.n, self.spectral);
+            }
+        }
+        self.image = Some(image);
+        self.group_images = group_images;
+        self.sample_counts = self.pixels.iter().map(|pixel| pixel.stats.n).collect();
+    }
 
-                    return match mat.scatter(r, &rec, rng) {
-                        Some((r, c)) => {
-                            emitted + c * Self::ray_color(r, background, world, rng, depth - 1)
-                        }
-                        None => emitted,
-                    };
+    /// Mean linear RGB of every pixel so far, row by row
+    pub fn estimate(&self) -> Vec<Vec3> {
+        self.pixels
+            .iter()
+            .map(|pixel| {
+                let mean = pixel.color / (pixel.stats.n.max(1) as f64);
+                if self.spectral {
+                    spectrum::xyz_to_rgb(mean)
+                } else {
+                    mean
                 }
-                None => rec.n, // No material found, default to color by normal
-            },
-            HitRec::Miss => *background,
+            })
+            .collect()
+    }
+
+    /// Traces sample `index` of `count` through `pixel`, adding what each light
+    /// group sees to `groups`. Spectral samples are returned as XYZ.
+    fn pixel_sample(
+        &self,
+        sampler: &mut dyn Sampler,
+        pixel: (usize, usize),
+        index: usize,
+        count: usize,
+        groups: &mut [Vec3],
+    ) -> Vec3 {
+        sampler.start_pixel_sample(pixel, index, count);
+        let (du, dv) = sampler.get_pixel_2d();
+        let u = (pixel.0 as f64 + du) / ((self.width - 1) as f64);
+        let v = (pixel.1 as f64 + dv) / ((self.height - 1) as f64);
+
+        // Differentials span a pixel shrunk by the spacing of the samples within it
+        let diff_scale = (1.0 / (count as f64).sqrt()).max(0.125);
+        let ds = diff_scale / ((self.width - 1) as f64);
+        let dt = diff_scale / ((self.height - 1) as f64);
+        let r = self
+            .scene
+            .camera
+            .get_ray_differential(u, v, ds, dt, sampler);
+
+        let mut sample_groups = vec![Vec3::zero(); groups.len()];
+        if self.spectral {
+            let lambda = spectrum::sample_wavelength(sampler);
+            let radiance = self.tracer(Some(lambda)).ray_color(
+                r,
+                sampler,
+                MAX_DEPTH,
+                Bounce::CAMERA,
+                &mut sample_groups,
+            );
+            for (color, sample) in groups.iter_mut().zip(&sample_groups) {
+                *color += spectrum::to_xyz(sample.x, lambda);
+            }
+            spectrum::to_xyz(radiance.x, lambda)
+        } else {
+            let radiance = self.tracer(None).ray_color(
+                r,
+                sampler,
+                MAX_DEPTH,
+                Bounce::CAMERA,
+                &mut sample_groups,
+            );
+            for (color, sample) in groups.iter_mut().zip(&sample_groups) {
+                *color += *sample;
+            }
+            radiance
+        }
+    }
+
+    /// Luminance of a sample as `pixel_sample` returns it
+    fn luminance(&self, c: Vec3) -> f64 {
+        if self.spectral {
+            c.y
+        } else {
+            c.luminance()
+        }
+    }
+
+    fn tracer(&self, lambda: Option<f64>) -> Tracer<'_> {
+        Tracer {
+            scene: &self.scene,
+            lights: &self.lights,
+            lambda,
         }
     }
 
-    fn write_color(buf: &mut Vec<u8>, v: Vec3, n_samples: usize) {
+    fn write_color(buf: &mut Vec<u8>, v: Vec3, n_samples: usize, spectral: bool) {
+        // Spectral samples are accumulated as XYZ
+        let v = if spectral { spectrum::xyz_to_rgb(v) } else { v };
         let scale = 1.0 / (n_samples as f64);
         let r = (v.x * scale).sqrt();
         let g = (v.y * scale).sqrt();
@@ -125,10 +282,43 @@ impl Renderer {
         let mut rng = Rng::new(1234);
         let (height, scene) = scene_gen.scene(self.width, &mut rng);
         self.height = height;
+        self.lights = LightTree::new(&scene.lights);
         self.scene = scene;
+        self.pixels_size = (0, 0);
     }
 
     pub fn write_image(&self, path: &Path) {
+        self.write_png(path, self.image.as_ref().unwrap());
+    }
+
+    /// Writes the image of each light group into `dir`, named after the group
+    pub fn write_light_groups(&self, dir: &Path) {
+        for (group, image) in self.scene.light_groups.iter().zip(&self.group_images) {
+            self.write_png(&dir.join(format!("{}.png", group.name)), image);
+        }
+    }
+
+    /// Writes how many samples each pixel took, from black for the fewest
+    /// through red and yellow to white for the most
+    pub fn write_sample_counts(&self, path: &Path) {
+        let min = self.sample_counts.iter().copied().min().unwrap_or(0);
+        let max = self.sample_counts.iter().copied().max().unwrap_or(0);
+        let data = self
+            .sample_counts
+            .iter()
+            .flat_map(|&n| {
+                let t = if max > min {
+                    3.0 * (n - min) as f64 / (max - min) as f64
+                } else {
+                    0.0
+                };
+                [t, t - 1.0, t - 2.0].map(|c| (255.0 * c.clamp(0.0, 1.0)) as u8)
+            })
+            .collect::<Vec<_>>();
+        self.write_png(path, &data);
+    }
+
+    fn write_png(&self, path: &Path, data: &[u8]) {
         let file = File::create(path).unwrap();
         let ref mut w = BufWriter::new(file);
         let mut encoder = png::Encoder::new(w, self.width as u32, self.height as u32);
@@ -136,8 +326,512 @@ impl Renderer {
         encoder.set_depth(png::BitDepth::Eight);
 
         let mut writer = encoder.write_header().unwrap();
-        writer
-            .write_image_data(self.image.as_ref().unwrap())
-            .unwrap();
+        writer.write_image_data(data).unwrap();
+    }
+}
+
+/// Running sums of the samples taken in a pixel
+#[derive(Clone)]
+struct Pixel {
+    /// Linear RGB, or XYZ when rendering spectrally
+    color: Vec3,
+    /// One sum per light group
+    groups: Vec<Vec3>,
+    /// Luminance statistics, counting the samples
+    stats: Welford,
+}
+
+impl Pixel {
+    fn new(n_groups: usize) -> Self {
+        Self {
+            color: Vec3::zero(),
+            groups: vec![Vec3::zero(); n_groups],
+            stats: Welford::default(),
+        }
+    }
+}
+
+/// Stops sampling a pixel once its luminance is known well enough
+#[derive(Debug, Clone, Copy)]
+pub struct AdaptiveSampling {
+    /// Most samples a pixel gets
+    pub max_samples: usize,
+    /// Widest allowed 95% confidence interval around the mean luminance of a
+    /// pixel, as a fraction of that luminance
+    pub threshold: f64,
+}
+
+impl AdaptiveSampling {
+    /// Darkest luminance the threshold is taken relative to, so black pixels can converge
+    const MIN_LUMINANCE: f64 = 0.01;
+
+    /// Most samples a pixel gets when passes take `n_samples`, which are never cut short
+    fn max_samples(&self, n_samples: usize) -> usize {
+        self.max_samples.max(n_samples)
+    }
+
+    fn noisy(&self, stats: &Welford) -> bool {
+        // A single sample says nothing about the spread
+        if stats.n < 2 {
+            return true;
+        }
+        let half_width = 1.96 * (stats.variance() / stats.n as f64).sqrt();
+        half_width > self.threshold * stats.mean.max(Self::MIN_LUMINANCE)
+    }
+}
+
+/// Running mean and variance, after Welford
+#[derive(Clone, Default)]
+struct Welford {
+    n: usize,
+    mean: f64,
+    /// Sum of squared differences from the mean
+    m2: f64,
+}
+
+impl Welford {
+    fn push(&mut self, x: f64) {
+        self.n += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.n as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    /// Unbiased sample variance
+    fn variance(&self) -> f64 {
+        if self.n > 1 {
+            self.m2 / (self.n - 1) as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The surface interaction a ray was scattered from
+#[derive(Clone, Copy)]
+struct Bounce {
+    n: Vec3,
+    /// Density the ray's direction was picked with, zero for camera rays and
+    /// specular bounces which light sampling can't find
+    pdf: f64,
+    /// Id of the object scattering the ray, for light linking
+    object: Option<usize>,
+    /// Product of the reflectances along the path up to the ray
+    throughput: Vec3,
+}
+
+impl Bounce {
+    const CAMERA: Self = Self {
+        n: Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        pdf: 0.0,
+        object: None,
+        throughput: Vec3 {
+            x: 1.0,
+            y: 1.0,
+            z: 1.0,
+        },
+    };
+}
+
+/// What a path needs to know about the scene while it is traced
+struct Tracer<'a> {
+    scene: &'a Scene,
+    /// Picks among the scene's lights
+    lights: &'a LightTree,
+    /// When set every channel of a color holds the radiance at this wavelength
+    lambda: Option<f64>,
+}
+
+impl Tracer<'_> {
+    /// Traces `r` through the world. `prev` is the bounce `r` leaves from, for
+    /// weighting what it finds against light sampling. What each scene light
+    /// contributes to the path is also added to the `groups` it belongs to.
+    fn ray_color(
+        &self,
+        r: Ray,
+        sampler: &mut dyn Sampler,
+        depth: usize,
+        prev: Bounce,
+        groups: &mut [Vec3],
+    ) -> Vec3 {
+        let (scene, lambda) = (self.scene, self.lambda);
+        if depth <= 0 {
+            return Vec3::zero();
+        }
+
+        match scene.world.hit(r, 0.001, f64::INFINITY) {
+            HitRec::Hit(mut rec, mat) => match mat {
+                Some(mat) => {
+                    rec.compute_footprint(r);
+                    let mut emitted = mat.emitted(r, &rec);
+                    if let Some(light) = rec.light {
+                        if depth < MAX_DEPTH && !scene.light_reaches(light, prev.object) {
+                            emitted = Vec3::zero();
+                        } else if prev.pdf > 0.0 {
+                            // Share the emitter with the light sample taken at the previous bounce
+                            let light_pdf = self.lights.pmf(r.o, prev.n, light)
+                                * scene.lights[light].pdf_li(r.o, r.d.unit());
+                            emitted = power_heuristic(prev.pdf, light_pdf) * emitted;
+                        }
+                    }
+                    let emitted = spectrum::illuminant(emitted, lambda);
+                    if let Some(light) = rec.light {
+                        self.add_to_groups(groups, light, prev.throughput * emitted);
+                    }
+
+                    let mut direct = self.sample_background(r, &rec, mat.as_ref(), sampler);
+                    if let Some((light, radiance)) =
+                        self.sample_lights(r, &rec, mat.as_ref(), sampler)
+                    {
+                        self.add_to_groups(groups, light, prev.throughput * radiance);
+                        direct += radiance;
+                    }
+
+                    return match mat.scatter(r, &rec, sampler) {
+                        Some(s) => {
+                            let reflectance = spectrum::reflectance(s.attenuation, lambda);
+                            let next = Bounce {
+                                n: rec.n,
+                                pdf: s.pdf,
+                                object: rec.object,
+                                throughput: prev.throughput * reflectance,
+                            };
+                            emitted
+                                + direct
+                                + reflectance
+                                    * self.ray_color(s.ray, sampler, depth - 1, next, groups)
+                        }
+                        None => emitted + direct,
+                    };
+                }
+                None => spectrum::reflectance(rec.n, lambda), // No material found, default to color by normal
+            },
+            HitRec::Miss => {
+                let dir = r.d.unit();
+                if depth == MAX_DEPTH {
+                    if let Some(background) = &scene.camera_background {
+                        return spectrum::illuminant(background.value(dir), lambda);
+                    }
+                }
+
+                // Share the background with the light sample taken at the previous bounce
+                let weight = if prev.pdf > 0.0 {
+                    power_heuristic(prev.pdf, scene.background.pdf(dir))
+                } else {
+                    1.0
+                };
+                weight * spectrum::illuminant(scene.background.value(dir), lambda)
+            }
+        }
+    }
+
+    fn add_to_groups(&self, groups: &mut [Vec3], light: usize, radiance: Vec3) {
+        for (group, color) in self.scene.light_groups.iter().zip(groups.iter_mut()) {
+            if group.lights.contains(&light) {
+                *color += radiance;
+            }
+        }
+    }
+
+    /// Light arriving at `rec` straight from the background, picked by importance
+    /// sampling the background and weighted against finding it by scattering
+    fn sample_background(
+        &self,
+        r: Ray,
+        rec: &Rec,
+        mat: &dyn Material,
+        sampler: &mut dyn Sampler,
+    ) -> Vec3 {
+        let (scene, lambda) = (self.scene, self.lambda);
+        let (wi, radiance, light_pdf) = match scene.background.sample(sampler) {
+            Some(sample) => sample,
+            None => return Vec3::zero(),
+        };
+
+        let f = mat.eval(r, rec, wi);
+        if f.near_zero() {
+            return Vec3::zero();
+        }
+
+        let shadow = Ray {
+            o: rec.p,
+            d: wi,
+            diff: None,
+        };
+        if let HitRec::Hit(..) = scene.world.hit(shadow, 0.001, f64::INFINITY) {
+            return Vec3::zero();
+        }
+
+        let weight = power_heuristic(light_pdf, mat.pdf(r, rec, wi));
+        spectrum::reflectance(weight / light_pdf * f, lambda)
+            * spectrum::illuminant(radiance, lambda)
+    }
+
+    /// Light arriving at `rec` from one of the scene lights, picked by its estimated
+    /// contribution. Returns the index of the light along with what it adds.
+    fn sample_lights(
+        &self,
+        r: Ray,
+        rec: &Rec,
+        mat: &dyn Material,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(usize, Vec3)> {
+        let (scene, lambda) = (self.scene, self.lambda);
+        let (light, pmf) = self.lights.sample(rec.p, rec.n, sampler.get_1d())?;
+        if !scene.light_reaches(light, rec.object) {
+            return None;
+        }
+        let sample = scene.lights[light].sample_li(rec.p, sampler)?;
+
+        let f = mat.eval(r, rec, sample.wi);
+        if f.near_zero() {
+            return None;
+        }
+
+        let shadow = Ray {
+            o: rec.p,
+            d: sample.wi,
+            diff: None,
+        };
+        if let HitRec::Hit(..) = scene.world.hit(shadow, 0.001, sample.dist - 0.001) {
+            return None;
+        }
+
+        // Delta lights can't be found by scattering so there is nothing to weight against
+        let weight = if sample.pdf > 0.0 {
+            let light_pdf = pmf * sample.pdf;
+            power_heuristic(light_pdf, mat.pdf(r, rec, sample.wi)) / light_pdf
+        } else {
+            1.0 / pmf
+        };
+        let radiance = spectrum::reflectance(weight * f, lambda)
+            * spectrum::illuminant(sample.radiance, lambda);
+        Some((light, radiance))
+    }
+}
+
+/// Multiple importance sampling weight of a sample with density `a`, drawn
+/// from one of two techniques whose other one has density `b` there
+fn power_heuristic(a: f64, b: f64) -> f64 {
+    let (a2, b2) = (a * a, b * b);
+    if a2 + b2 > 0.0 {
+        a2 / (a2 + b2)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::*;
+    use camera::Camera;
+    use hittable::{HittableList, Sphere, Tagged};
+    use light::{Light, LightGroup, LightLink, PointLight};
+    use material::Lambertian;
+    use texture::{SolidColor, Texture};
+
+    #[test]
+    fn adaptive() {
+        let mut stats = Welford::default();
+        for &x in [1.0, 2.0, 4.0, 5.0].iter() {
+            stats.push(x);
+        }
+        assert!((stats.mean - 3.0).abs() < 1e-12);
+        assert!((stats.variance() - 10.0 / 3.0).abs() < 1e-12);
+
+        let adaptive = AdaptiveSampling {
+            max_samples: 64,
+            threshold: 0.5,
+        };
+        assert!(adaptive.noisy(&stats));
+        let mut single = Welford::default();
+        single.push(1.0);
+        assert!(adaptive.noisy(&single));
+        for _ in 0..60 {
+            stats.push(3.0);
+        }
+        assert!(!adaptive.noisy(&stats));
+    }
+
+    /// A diffuse sphere in front of a gray sky
+    struct TinyScene {}
+
+    impl SceneTrait for TinyScene {
+        fn scene(&self, width: usize, _rng: &mut Rng) -> (usize, Scene) {
+            let mut world = HittableList::new();
+            world.push(Sphere {
+                c: Vec3::zero(),
+                r: 1.0,
+                mat: Some(Arc::new(Lambertian {
+                    albedo: Arc::new(SolidColor {
+                        color_value: Vec3 {
+                            x: 0.5,
+                            y: 0.5,
+                            z: 0.5,
+                        },
+                    }) as Arc<dyn Texture>,
+                })),
+            });
+            let look_from = Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: 4.0,
+            };
+            let scene = Scene {
+                world,
+                camera: Camera::new(look_from, Vec3::zero(), 40.0, 1.0, 0.0, 4.0),
+                background: Arc::new(Vec3 {
+                    x: 0.6,
+                    y: 0.6,
+                    z: 0.6,
+                }),
+                ..Scene::default()
+            };
+            (width, scene)
+        }
+    }
+
+    /// TinyScene against black, lit by a point light that can be grouped or
+    /// linked away from the sphere
+    struct LitScene {
+        link: Option<LightLink>,
+    }
+
+    impl SceneTrait for LitScene {
+        fn scene(&self, width: usize, rng: &mut Rng) -> (usize, Scene) {
+            let (height, tiny) = TinyScene {}.scene(width, rng);
+            let mut world = HittableList::new();
+            world.push(Tagged {
+                object: Box::new(tiny.world),
+                id: 0,
+            });
+            let light: Arc<dyn Light> = Arc::new(PointLight {
+                position: Vec3 {
+                    x: 2.0,
+                    y: 2.0,
+                    z: 4.0,
+                },
+                intensity: Vec3::splat(10.0),
+                profile: None,
+            });
+            let mut light_links = HashMap::new();
+            if let Some(link) = &self.link {
+                light_links.insert(0, link.clone());
+            }
+            let scene = Scene {
+                world,
+                camera: tiny.camera,
+                lights: vec![light],
+                light_groups: vec![LightGroup {
+                    name: "key".to_string(),
+                    lights: vec![0],
+                }],
+                light_links,
+                ..Scene::default()
+            };
+            (height, scene)
+        }
+    }
+
+    #[test]
+    fn light_groups() {
+        let mut renderer = Renderer::new(8);
+        renderer.scene(LitScene { link: None });
+        renderer.render(4);
+        let image = renderer.image.as_ref().unwrap();
+        assert!(image.iter().any(|&c| c > 0));
+        // Summed in a different order, so allow for rounding
+        for (&a, &b) in image.iter().zip(&renderer.group_images[0]) {
+            assert!((a as i32 - b as i32).abs() <= 1, "{} {}", a, b);
+        }
+
+        renderer.scene(LitScene {
+            link: Some(LightLink::Except(vec![0])),
+        });
+        renderer.render(4);
+        assert!(renderer.image.as_ref().unwrap().iter().all(|&c| c == 0));
+        assert!(renderer.group_images[0].iter().all(|&c| c == 0));
+    }
+
+    #[test]
+    fn camera_background() {
+        struct Backdrop {}
+
+        impl SceneTrait for Backdrop {
+            fn scene(&self, width: usize, rng: &mut Rng) -> (usize, Scene) {
+                let (height, tiny) = TinyScene {}.scene(width, rng);
+                let scene = Scene {
+                    camera_background: Some(Arc::new(Vec3::zero())),
+                    ..tiny
+                };
+                (height, scene)
+            }
+        }
+
+        // The camera sees black around a sphere the gray sky still lights
+        let mut renderer = Renderer::new(8);
+        renderer.scene(Backdrop {});
+        renderer.render(4);
+        let image = renderer.image.as_ref().unwrap();
+        assert_eq!(&image[..3], &[0, 0, 0]);
+        let center = 3 * (4 * 8 + 4);
+        assert!(image[center] > 0);
+    }
+
+    #[test]
+    fn progressive() {
+        let mut renderer = Renderer::new(6);
+        renderer.scene(TinyScene {});
+        let mut passes = Vec::new();
+        renderer.render_progressive(2, 2, |r, pass| {
+            passes.push((pass, r.sample_counts.clone()));
+        });
+        assert_eq!(passes.len(), 2);
+        assert!(passes[0].1.iter().all(|&n| n == 2));
+        assert!(passes[1].1.iter().all(|&n| n == 4));
+
+        // The image is the estimate, gamma corrected and quantized
+        let image = renderer.image.as_ref().unwrap();
+        for (pixel, rgb) in renderer.estimate().iter().zip(image.chunks(3)) {
+            for (&c, &byte) in [pixel.x, pixel.y, pixel.z].iter().zip(rgb) {
+                assert_eq!((256.0 * c.sqrt().clamp(0.0, 0.9999)) as u8, byte);
+            }
+        }
+
+        // Resuming adds to the buffer, anything else starts over
+        renderer.render_progressive(2, 1, |_, _| {});
+        assert!(renderer.sample_counts.iter().all(|&n| n == 6));
+        renderer.scene(TinyScene {});
+        renderer.render_progressive(2, 1, |_, _| {});
+        assert!(renderer.sample_counts.iter().all(|&n| n == 2));
+        renderer.render(3);
+        renderer.render_progressive(2, 1, |_, _| {});
+        assert!(renderer.sample_counts.iter().all(|&n| n == 5));
+        renderer.width = 4;
+        renderer.render_progressive(2, 1, |_, _| {});
+        assert_eq!(renderer.sample_counts.len(), 4 * 6);
+        assert!(renderer.sample_counts.iter().all(|&n| n == 2));
+    }
+
+    #[test]
+    fn adaptive_render_ends() {
+        let mut renderer = Renderer::new(4);
+        renderer.scene(TinyScene {});
+        renderer.adaptive = Some(AdaptiveSampling {
+            max_samples: 0,
+            threshold: 0.0,
+        });
+        // A cap below the first pass still takes the whole pass
+        renderer.render(3);
+        assert!(renderer.sample_counts.iter().all(|&n| n == 3));
+        renderer.render(0);
+        assert!(renderer.sample_counts.iter().all(|&n| n == 0));
     }
 }
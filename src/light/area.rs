@@ -0,0 +1,379 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use super::{Light, LightBounds, LightSample};
+use crate::distribution::Distribution2D;
+use crate::geometry::{Ray, Vec3};
+use crate::hittable::{area_to_solid_angle, HitRec, Hittable, HittableList, Rec, Shape, AABB};
+use crate::sampler::Sampler;
+
+/// Emissive geometry sampled over its surface. It is part of the world too,
+/// marking its hits so what scattered rays find is weighted against sampling it.
+#[derive(Debug, Clone)]
+pub struct AreaLight {
+    pub shape: Arc<dyn Shape>,
+    /// Index of this light in the scene lights
+    pub index: usize,
+    /// Picks the `u` of `surface_point` by emitted luminance, `None` when the
+    /// emission is the same everywhere and the shape samples itself
+    distribution: Option<Distribution2D>,
+}
+
+impl AreaLight {
+    pub fn new(shape: Arc<dyn Shape>, index: usize) -> Self {
+        let mut light = Self {
+            shape,
+            index,
+            distribution: None,
+        };
+
+        const N: usize = 32;
+        let mut func = Vec::with_capacity(N * N);
+        for j in 0..N {
+            for i in 0..N {
+                let u = ((i as f64 + 0.5) / N as f64, (j as f64 + 0.5) / N as f64);
+                let (front, back) = light.luminance_at(u);
+                func.push(front + back);
+            }
+        }
+        let max = func.iter().cloned().fold(0.0, f64::max);
+        let min = func.iter().cloned().fold(max, f64::min);
+        if max - min > 1e-6 * max {
+            // Dark cells keep a little weight as the emission between grid points is unknown
+            let floor = 0.01 * func.iter().sum::<f64>() / func.len() as f64;
+            let func = func.iter().map(|&f| f.max(floor)).collect::<Vec<_>>();
+            light.distribution = Some(Distribution2D::new(&func, N, N));
+        }
+        light
+    }
+
+    fn emitted(&self, r_in: Ray, rec: &Rec) -> Vec3 {
+        match self.shape.material() {
+            Some(mat) => mat.emitted(r_in, rec),
+            None => Vec3::zero(),
+        }
+    }
+
+    /// Luminance emitted head on from the front and the back at `surface_point(u)`
+    fn luminance_at(&self, u: (f64, f64)) -> (f64, f64) {
+        let rec = self.shape.surface_point(u);
+        let towards = |n: Vec3| Ray {
+            o: rec.p + n,
+            d: -n,
+            diff: None,
+        };
+        let behind = Rec {
+            n: -rec.n,
+            front_face: false,
+            ..rec
+        };
+        (
+            self.emitted(towards(rec.n), &rec).luminance(),
+            self.emitted(towards(-rec.n), &behind).luminance(),
+        )
+    }
+
+    fn hit_from(&self, p: Vec3, wi: Vec3) -> Option<Rec> {
+        let r = Ray {
+            o: p,
+            d: wi,
+            diff: None,
+        };
+        match self.shape.hit(r, 1e-6, f64::INFINITY) {
+            HitRec::Hit(rec, _) => Some(rec),
+            HitRec::Miss => None,
+        }
+    }
+}
+
+/// Adds `shape` to the world and to `lights` as an area light
+pub fn push_area_light<S: Shape + 'static>(
+    world: &mut HittableList,
+    lights: &mut Vec<Arc<dyn Light>>,
+    shape: S,
+) {
+    let light = AreaLight::new(Arc::new(shape), lights.len());
+    world.push(light.clone());
+    lights.push(Arc::new(light));
+}
+
+impl Hittable for AreaLight {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> HitRec<'_> {
+        self.shape.hit(r, t_min, t_max).with_light(self.index)
+    }
+
+    fn aabb(&self, t0: f64, t1: f64) -> Option<AABB> {
+        self.shape.aabb(t0, t1)
+    }
+}
+
+impl Light for AreaLight {
+    fn sample_li(&self, p: Vec3, sampler: &mut dyn Sampler) -> Option<LightSample> {
+        let (rec, pdf) = match &self.distribution {
+            Some(distribution) => {
+                let (u, pdf) = distribution.sample(sampler.get_2d());
+                let q = self.shape.surface_point(u).p;
+                let rec = self.hit_from(p, (q - p).unit())?;
+                // Points on the far side of the shape are in its own shadow
+                if (rec.p - q).len_sq() > 1e-6 * (q - p).len_sq() {
+                    return None;
+                }
+                let pdf = pdf * area_to_solid_angle(p, &rec, self.shape.area());
+                if pdf <= 0.0 {
+                    return None;
+                }
+                (rec, pdf)
+            }
+            None => self.shape.sample(p, sampler)?,
+        };
+        let wi = (rec.p - p).unit();
+        let r_in = Ray {
+            o: p,
+            d: wi,
+            diff: None,
+        };
+
+        let radiance = self.emitted(r_in, &rec);
+        if radiance.near_zero() {
+            return None;
+        }
+        Some(LightSample {
+            wi,
+            radiance,
+            dist: (rec.p - p).len(),
+            pdf,
+        })
+    }
+
+    fn pdf_li(&self, p: Vec3, wi: Vec3) -> f64 {
+        let distribution = match &self.distribution {
+            Some(d) => d,
+            None => return self.shape.pdf(p, wi),
+        };
+        match self.hit_from(p, wi) {
+            Some(rec) => {
+                let (x, y) = self.shape.surface_coords(rec.p);
+                distribution.pdf(x, y) * area_to_solid_angle(p, &rec, self.shape.area())
+            }
+            None => 0.0,
+        }
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        // Power from the emission averaged over a grid of points on both sides,
+        // so textured emitters are weighted by how bright they are
+        const N: usize = 8;
+        let (mut front, mut back) = (0.0, 0.0);
+        for i in 0..N {
+            for j in 0..N {
+                let u = ((i as f64 + 0.5) / N as f64, (j as f64 + 0.5) / N as f64);
+                let (f, b) = self.luminance_at(u);
+                front += f;
+                back += b;
+            }
+        }
+        let power = PI * self.shape.area() * (front + back) / (N * N) as f64;
+
+        let (axis, cos_theta_o) = self.shape.normal_cone();
+        Some(LightBounds {
+            bounds: self.shape.aabb(0.0, 0.0)?,
+            power,
+            axis,
+            cos_theta_o,
+            cos_theta_e: 0.0,
+            two_sided: back > 0.0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::Sphere;
+    use crate::material::DiffuseLight;
+    use crate::rng::Rng;
+    use crate::texture::{LinearGradient, SolidColor, TextureSpace};
+
+    fn emitter(two_sided: bool) -> DiffuseLight {
+        DiffuseLight {
+            emit: Arc::new(SolidColor {
+                color_value: Vec3::splat(2.0),
+            }),
+            profile: None,
+            two_sided,
+        }
+    }
+
+    #[test]
+    fn sphere_light() {
+        let mut world = HittableList::new();
+        let mut lights = Vec::new();
+        push_area_light(
+            &mut world,
+            &mut lights,
+            Sphere {
+                c: Vec3 {
+                    x: 0.0,
+                    y: 3.0,
+                    z: 0.0,
+                },
+                r: 1.0,
+                mat: Some(Arc::new(emitter(false))),
+            },
+        );
+        let light = &lights[0];
+
+        // Irradiance under a uniform sphere is pi L sin^2 of its half angle
+        let n = Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        };
+        let mut rng = Rng::new(7);
+        let samples = 20000;
+        let mut irradiance = 0.0;
+        for _ in 0..samples {
+            let s = light.sample_li(Vec3::zero(), &mut rng).unwrap();
+            assert!((s.pdf - light.pdf_li(Vec3::zero(), s.wi)).abs() < 1e-9 * s.pdf);
+            irradiance += s.radiance.x * Vec3::dot(s.wi, n) / s.pdf;
+        }
+        irradiance /= samples as f64;
+        let exact = PI * 2.0 / 9.0;
+        assert!((irradiance - exact).abs() < 0.01 * exact);
+
+        // Hits on the light say which light they are
+        let r = Ray {
+            o: Vec3::zero(),
+            d: n,
+            diff: None,
+        };
+        match world.hit(r, 0.001, f64::INFINITY) {
+            HitRec::Hit(rec, _) => assert_eq!(rec.light, Some(0)),
+            HitRec::Miss => panic!("missed the light"),
+        }
+
+        let bounds = light.bounds().unwrap();
+        assert!((bounds.power - PI * 4.0 * PI * 2.0).abs() < 1e-9);
+        assert!(!bounds.two_sided);
+    }
+
+    #[test]
+    fn one_sided_quad() {
+        let mut world = HittableList::new();
+        let mut lights = Vec::new();
+        let x = Vec3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        let z = Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        };
+        // Facing down
+        for t in
+            crate::hittable::Triangle::quad(Vec3::zero(), x, z, 1, Some(Arc::new(emitter(false))))
+        {
+            push_area_light(&mut world, &mut lights, t);
+        }
+
+        let below = Vec3 {
+            x: 0.5,
+            y: -1.0,
+            z: 0.5,
+        };
+        let mut rng = Rng::new(1);
+        assert!(lights[0].sample_li(below, &mut rng).is_some());
+        assert!(lights[0].sample_li(-1.0 * below, &mut rng).is_none());
+
+        let bounds = lights[0].bounds().unwrap();
+        assert_eq!(bounds.axis.y, -1.0);
+        assert!(bounds.importance(below, Vec3::zero()) > 0.0);
+        assert_eq!(
+            bounds.importance(
+                Vec3 {
+                    x: 0.5,
+                    y: 1.0,
+                    z: 0.5
+                },
+                Vec3::zero()
+            ),
+            0.0
+        );
+    }
+
+    #[test]
+    fn textured_quad() {
+        let mut world = HittableList::new();
+        let mut lights = Vec::new();
+        let x = Vec3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        let z = Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 1.0,
+        };
+        // A screen brightening along u, which runs along x
+        let screen = DiffuseLight {
+            emit: Arc::new(LinearGradient {
+                space: TextureSpace::Uv,
+                start: Vec3::zero(),
+                end: x,
+                from: Vec3::zero(),
+                to: Vec3::splat(1.0),
+            }),
+            profile: None,
+            two_sided: true,
+        };
+        for t in crate::hittable::Triangle::quad(Vec3::zero(), x, z, 1, Some(Arc::new(screen))) {
+            push_area_light(&mut world, &mut lights, t);
+        }
+
+        let below = Vec3 {
+            x: 0.5,
+            y: -1.0,
+            z: 0.5,
+        };
+        let mut rng = Rng::new(3);
+        let samples = 5000;
+        let (mut dim, mut bright, mut irradiance) = (0, 0, 0.0);
+        for light in lights.iter() {
+            for _ in 0..samples {
+                let s = light.sample_li(below, &mut rng).unwrap();
+                let q = below + s.dist * s.wi;
+                assert!((s.radiance.x - q.x).abs() < 1e-9);
+                assert!((s.pdf - light.pdf_li(below, s.wi)).abs() < 1e-6 * s.pdf);
+                if s.radiance.x < 0.5 {
+                    dim += 1;
+                } else {
+                    bright += 1;
+                }
+                irradiance += s.radiance.x * s.wi.y / s.pdf;
+            }
+        }
+        irradiance /= samples as f64;
+        // Sampling follows the emission, three quarters of it is on the bright half
+        assert!(bright > 2 * dim && dim > 0);
+
+        // Midpoint rule for the integral of L cos cos / d^2 over the screen
+        let n = 200;
+        let mut exact = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                let q = Vec3 {
+                    x: (i as f64 + 0.5) / n as f64,
+                    y: 0.0,
+                    z: (j as f64 + 0.5) / n as f64,
+                };
+                let d_sq = (q - below).len_sq();
+                exact += q.x / (d_sq * d_sq);
+            }
+        }
+        exact /= (n * n) as f64;
+        assert!((irradiance - exact).abs() < 0.02 * exact);
+    }
+}
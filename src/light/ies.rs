@@ -0,0 +1,313 @@
+//! IES LM-63 photometric profiles, the luminous intensity a fixture emits in
+//! each direction as measured by its manufacturer.
+//!
+//! Only type C photometry is supported, which covers nearly all architectural
+//! fixtures. Vertical angles go from 0° straight down to 180° straight up,
+//! horizontal angles turn around the vertical axis. Lamp tilt data is skipped.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::geometry::Vec3;
+
+/// Luminous efficacy of monochromatic 555 nm light, converts candela to watts per steradian
+pub const LUMENS_PER_WATT: f64 = 683.0;
+
+#[derive(Debug)]
+pub enum IesError {
+    /// The file doesn't follow the LM-63 layout
+    Syntax(String),
+    /// The file could not be read
+    Io(std::io::Error),
+}
+
+impl fmt::Display for IesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IesError::Syntax(message) => write!(f, "invalid IES file: {}", message),
+            IesError::Io(error) => write!(f, "failed to read IES file: {}", error),
+        }
+    }
+}
+
+impl Error for IesError {}
+
+/// Candela values on a grid of vertical and horizontal angles
+#[derive(Debug, Clone)]
+pub struct IesProfile {
+    /// Vertical angles in degrees, ascending
+    vertical: Vec<f64>,
+    /// Horizontal angles in degrees, ascending
+    horizontal: Vec<f64>,
+    /// One row of vertical samples per horizontal angle, multipliers applied
+    candela: Vec<f64>,
+    max_candela: f64,
+}
+
+impl IesProfile {
+    pub fn parse(src: &str) -> Result<Self, IesError> {
+        // Header and keyword lines up to the tilt line, then numbers only
+        let mut lines = src.lines();
+        let tilt = loop {
+            match lines.next() {
+                Some(line) if line.trim_start().starts_with("TILT=") => {
+                    break line.trim_start()["TILT=".len()..].trim().to_string()
+                }
+                Some(_) => continue,
+                None => return Err(syntax("missing TILT line")),
+            }
+        };
+        let rest = lines.collect::<Vec<_>>().join("\n");
+        let mut numbers = rest
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                s.parse::<f64>()
+                    .map_err(|_| syntax(&format!("bad number '{}'", s)))
+            });
+        let mut next = || {
+            numbers
+                .next()
+                .unwrap_or_else(|| Err(syntax("truncated data")))
+        };
+
+        if tilt == "INCLUDE" {
+            let _geometry = next()?;
+            let pairs = next()? as usize;
+            for _ in 0..2 * pairs {
+                next()?;
+            }
+        }
+
+        let _lamps = next()?;
+        let _lumens_per_lamp = next()?;
+        let multiplier = next()?;
+        let n_vertical = next()? as usize;
+        let n_horizontal = next()? as usize;
+        let photometric_type = next()?;
+        let _units = next()?;
+        for _ in 0..3 {
+            next()?; // Luminous opening width, length, height
+        }
+        let ballast_factor = next()?;
+        let lamp_factor = next()?;
+        let _watts = next()?;
+
+        if photometric_type != 1.0 {
+            return Err(syntax("only type C photometry is supported"));
+        }
+        if n_vertical == 0 || n_horizontal == 0 {
+            return Err(syntax("empty angle grid"));
+        }
+
+        let mut read = |n: usize| (0..n).map(|_| next()).collect::<Result<Vec<_>, _>>();
+        let vertical = read(n_vertical)?;
+        let horizontal = read(n_horizontal)?;
+        let scale = multiplier * ballast_factor * lamp_factor;
+        let candela = read(n_vertical * n_horizontal)?
+            .into_iter()
+            .map(|c| scale * c)
+            .collect::<Vec<_>>();
+
+        let ascending = |a: &[f64]| a.windows(2).all(|w| w[0] < w[1]);
+        if !ascending(&vertical) || !ascending(&horizontal) {
+            return Err(syntax("angles must be ascending"));
+        }
+
+        let max_candela = candela.iter().fold(0.0f64, |m, &c| m.max(c));
+        Ok(Self {
+            vertical,
+            horizontal,
+            candela,
+            max_candela,
+        })
+    }
+
+    pub fn open(path: &Path) -> Result<Self, IesError> {
+        Self::parse(&fs::read_to_string(path).map_err(IesError::Io)?)
+    }
+
+    /// Brightest value in the profile, in candela
+    pub fn max_candela(&self) -> f64 {
+        self.max_candela
+    }
+
+    /// Luminous intensity in candela at a vertical and horizontal angle in degrees
+    pub fn candela(&self, vertical: f64, horizontal: f64) -> f64 {
+        let (v, tv) = match interval(&self.vertical, vertical) {
+            Some(i) => i,
+            None => return 0.0,
+        };
+
+        // Profiles only store the part of the circle their symmetry needs
+        let last = *self.horizontal.last().unwrap();
+        let mut h = horizontal.rem_euclid(360.0);
+        if last <= 180.0 && h > 180.0 {
+            h = 360.0 - h;
+        }
+        if last <= 90.0 && h > 90.0 {
+            h = 180.0 - h;
+        }
+        let (h, th) = interval(&self.horizontal, h).unwrap_or_else(|| {
+            if h < self.horizontal[0] {
+                (0, 0.0)
+            } else {
+                (self.horizontal.len() - 1, 0.0)
+            }
+        });
+
+        let n = self.vertical.len();
+        let at = |h: usize, v: usize| self.candela[h * n + v];
+        let row = |h: usize| {
+            if tv > 0.0 {
+                (1.0 - tv) * at(h, v) + tv * at(h, v + 1)
+            } else {
+                at(h, v)
+            }
+        };
+        if th > 0.0 {
+            (1.0 - th) * row(h) + th * row(h + 1)
+        } else {
+            row(h)
+        }
+    }
+}
+
+/// Index and fraction of the pair of `angles` around `x`, `None` outside of them
+fn interval(angles: &[f64], x: f64) -> Option<(usize, f64)> {
+    if x < angles[0] || x > *angles.last().unwrap() {
+        return None;
+    }
+    let i = angles.partition_point(|&a| a <= x).max(1) - 1;
+    if i + 1 == angles.len() {
+        return Some((i, 0.0));
+    }
+    Some((i, (x - angles[i]) / (angles[i + 1] - angles[i])))
+}
+
+fn syntax(message: &str) -> IesError {
+    IesError::Syntax(message.to_string())
+}
+
+/// Places a photometric profile in the scene and turns its candela into
+/// radiant intensity, for point lights and emissive surfaces.
+#[derive(Debug, Clone)]
+pub struct EmissionProfile {
+    pub profile: Arc<IesProfile>,
+    /// Direction of the 0° vertical angle, where the fixture points
+    pub down: Vec3,
+    /// Turns the profile around `down`, in degrees
+    pub rotation: f64,
+    /// Multiplies candela into the renderer's radiance units
+    pub scale: f64,
+    /// Area of the emitting surface when shaping a `DiffuseLight`
+    pub area: f64,
+}
+
+impl EmissionProfile {
+    /// Points the fixture straight down and converts candela to watts per steradian
+    pub fn new(profile: Arc<IesProfile>) -> Self {
+        Self {
+            profile,
+            down: Vec3 {
+                x: 0.0,
+                y: -1.0,
+                z: 0.0,
+            },
+            rotation: 0.0,
+            scale: 1.0 / LUMENS_PER_WATT,
+            area: 1.0,
+        }
+    }
+
+    /// Radiant intensity emitted towards the unit direction `w`
+    pub fn intensity(&self, w: Vec3) -> f64 {
+        let down = self.down.unit();
+        let a = if down.x.abs() > 0.9 {
+            Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: 1.0,
+            }
+        } else {
+            Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            }
+        };
+        // 0° horizontal along x, 90° along z for a fixture pointing down
+        let x = (a - Vec3::dot(a, down) * down)
+            .unit()
+            .rotate_axis_angle(down, -self.rotation.to_radians());
+        let z = Vec3::cross(down, x);
+
+        let vertical = Vec3::dot(w, down).clamp(-1.0, 1.0).acos().to_degrees();
+        let horizontal = Vec3::dot(w, z).atan2(Vec3::dot(w, x)).to_degrees();
+        self.scale * self.profile.candela(vertical, horizontal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOWNLIGHT: &str = "IESNA:LM-63-2002
+[TEST] downlight
+[MANUFAC] nobody
+TILT=NONE
+1 1000 2.0 3 2 1 2 0.1 0.1 0
+1.0 1.0 20
+0 45 90
+0 90
+100, 50, 0
+100, 30, 0
+";
+
+    #[test]
+    fn parse() {
+        let ies = IesProfile::parse(DOWNLIGHT).unwrap();
+        assert_eq!(ies.max_candela(), 200.0);
+        assert_eq!(ies.candela(0.0, 0.0), 200.0);
+        assert_eq!(ies.candela(22.5, 0.0), 150.0);
+        assert_eq!(ies.candela(45.0, 45.0), 80.0);
+        assert_eq!(ies.candela(120.0, 0.0), 0.0);
+
+        // Quadrant symmetry mirrors the stored 0° to 90°
+        assert_eq!(ies.candela(45.0, 270.0), 60.0);
+        assert_eq!(ies.candela(45.0, 180.0), 100.0);
+
+        assert!(IesProfile::parse("TILT=NONE\n1 1000 1 2 1").is_err());
+        assert!(IesProfile::parse(&DOWNLIGHT.replace("3 2 1 2 0.1", "3 2 3 2 0.1")).is_err());
+    }
+
+    #[test]
+    fn orientation() {
+        let mut profile = EmissionProfile::new(Arc::new(IesProfile::parse(DOWNLIGHT).unwrap()));
+        profile.scale = 1.0;
+        let side = |deg: f64| {
+            let t = deg.to_radians();
+            Vec3 {
+                x: t.sin(),
+                y: -t.cos(),
+                z: 0.0,
+            }
+        };
+        assert!((profile.intensity(side(0.0)) - 200.0).abs() < 1e-9);
+        assert!((profile.intensity(side(45.0)) - 100.0).abs() < 1e-9);
+
+        // Turning the fixture by 90° swaps the x and z planes
+        profile.rotation = 90.0;
+        assert!((profile.intensity(side(45.0)) - 60.0).abs() < 1e-9);
+
+        profile.down = Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        };
+        assert!((profile.intensity(side(180.0)) - 200.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,324 @@
+use std::f64::consts::PI;
+use std::fmt::Debug;
+
+use crate::geometry::Vec3;
+use crate::sampler::Sampler;
+
+mod area;
+mod ies;
+mod tree;
+
+pub use area::{push_area_light, AreaLight};
+pub use ies::{EmissionProfile, IesError, IesProfile, LUMENS_PER_WATT};
+pub use tree::{LightBounds, LightTree};
+
+/// Light arriving at a point from one direction
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    /// Unit direction towards the light
+    pub wi: Vec3,
+    /// Incident radiance
+    pub radiance: Vec3,
+    /// Distance to the light, infinite for directional lights
+    pub dist: f64,
+    /// Solid angle density `wi` was picked with, zero for delta lights
+    /// which scattered rays can never find
+    pub pdf: f64,
+}
+
+/// Total power emitted by a light
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Power {
+    /// Radiant power, with white light having a luminance matching its radiance
+    Watts(f64),
+    /// Luminous power, converted to watts at `LUMENS_PER_WATT`
+    Lumens(f64),
+}
+
+impl Power {
+    pub fn watts(self) -> f64 {
+        match self {
+            Power::Watts(w) => w,
+            Power::Lumens(lm) => lm / LUMENS_PER_WATT,
+        }
+    }
+}
+
+/// A light the integrator samples explicitly. Delta lights are only found by
+/// sampling them from the surfaces they light, area lights can be hit by rays too.
+pub trait Light: Send + Sync + Debug {
+    /// Light arriving at `p`, `None` when `p` is outside of its reach
+    fn sample_li(&self, p: Vec3, sampler: &mut dyn Sampler) -> Option<LightSample>;
+
+    /// Solid angle density `sample_li` picks the unit direction `wi` from `p` with
+    fn pdf_li(&self, _p: Vec3, _wi: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Extent, power and facing of the light for picking it among others.
+    /// `None` for lights infinitely far away.
+    fn bounds(&self) -> Option<LightBounds>;
+}
+
+/// Emits from a single point, equally in all directions unless shaped by a profile
+#[derive(Debug, Clone)]
+pub struct PointLight {
+    pub position: Vec3,
+    /// Radiant intensity, power per solid angle. Tints the profile when there is one.
+    pub intensity: Vec3,
+    pub profile: Option<EmissionProfile>,
+}
+
+impl Light for PointLight {
+    fn sample_li(&self, p: Vec3, _sampler: &mut dyn Sampler) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let dist_sq = to_light.len_sq();
+        // No direction to a light at the point itself
+        if dist_sq == 0.0 || !dist_sq.is_finite() {
+            return None;
+        }
+        let dist = dist_sq.sqrt();
+        let wi = to_light / dist;
+
+        let intensity = match &self.profile {
+            Some(profile) => profile.intensity(-wi) * self.intensity,
+            None => self.intensity,
+        };
+        Some(LightSample {
+            wi,
+            radiance: intensity / dist_sq,
+            dist,
+            pdf: 0.0,
+        })
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        let scale = match &self.profile {
+            Some(profile) => profile.scale * profile.profile.max_candela(),
+            None => 1.0,
+        };
+        Some(LightBounds::point(
+            self.position,
+            4.0 * PI * scale * self.intensity.luminance(),
+        ))
+    }
+}
+
+/// A point light emitting in a cone. Full intensity inside of `inner_angle`,
+/// smoothly falling off to nothing at `outer_angle`.
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    pub position: Vec3,
+    /// Direction the cone points towards
+    pub direction: Vec3,
+    /// Radiant intensity along the axis of the cone
+    pub intensity: Vec3,
+    /// Angle from the axis where the falloff starts, in degrees
+    pub inner_angle: f64,
+    /// Angle from the axis where the light ends, in degrees
+    pub outer_angle: f64,
+}
+
+impl SpotLight {
+    /// Fraction of the intensity emitted towards the unit direction `w`
+    fn falloff(&self, w: Vec3) -> f64 {
+        let cos_theta = Vec3::dot(w, self.direction.unit());
+        let cos_inner = self.inner_angle.to_radians().cos();
+        let cos_outer = self.outer_angle.to_radians().cos();
+        if cos_theta >= cos_inner {
+            return 1.0;
+        }
+        if cos_theta <= cos_outer {
+            return 0.0;
+        }
+
+        let t = (cos_theta - cos_outer) / (cos_inner - cos_outer);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample_li(&self, p: Vec3, _sampler: &mut dyn Sampler) -> Option<LightSample> {
+        let to_light = self.position - p;
+        let dist_sq = to_light.len_sq();
+        // No direction to a light at the point itself
+        if dist_sq == 0.0 || !dist_sq.is_finite() {
+            return None;
+        }
+        let dist = dist_sq.sqrt();
+        let wi = to_light / dist;
+
+        let falloff = self.falloff(-wi);
+        if falloff <= 0.0 {
+            return None;
+        }
+        Some(LightSample {
+            wi,
+            radiance: falloff * self.intensity / dist_sq,
+            dist,
+            pdf: 0.0,
+        })
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        let inner = self.inner_angle.to_radians();
+        let outer = self.outer_angle.to_radians();
+        Some(LightBounds {
+            axis: self.direction.unit(),
+            cos_theta_o: inner.cos(),
+            cos_theta_e: (outer - inner).cos(),
+            // Power within the cone, counting the falloff as half
+            ..LightBounds::point(
+                self.position,
+                PI * (2.0 - inner.cos() - outer.cos()) * self.intensity.luminance(),
+            )
+        })
+    }
+}
+
+/// Parallel light from infinitely far away, like the sun
+#[derive(Debug, Clone)]
+pub struct DirectionalLight {
+    /// Direction pointing towards the light
+    pub direction: Vec3,
+    /// Irradiance on a surface facing the light
+    pub irradiance: Vec3,
+}
+
+impl Light for DirectionalLight {
+    fn sample_li(&self, _p: Vec3, _sampler: &mut dyn Sampler) -> Option<LightSample> {
+        Some(LightSample {
+            wi: self.direction.unit(),
+            radiance: self.irradiance,
+            dist: f64::INFINITY,
+            pdf: 0.0,
+        })
+    }
+
+    fn bounds(&self) -> Option<LightBounds> {
+        None
+    }
+}
+
+/// A named set of lights whose contribution is also rendered into its own image
+#[derive(Debug, Clone)]
+pub struct LightGroup {
+    pub name: String,
+    /// Indices into the scene lights
+    pub lights: Vec<usize>,
+}
+
+/// Restricts which objects a light shines on, by the ids given with `Tagged`
+#[derive(Debug, Clone)]
+pub enum LightLink {
+    /// Only these objects are lit
+    Only(Vec<usize>),
+    /// Everything but these objects is lit
+    Except(Vec<usize>),
+}
+
+impl LightLink {
+    /// Whether a surface of the object with id `object` is lit
+    pub fn reaches(&self, object: Option<usize>) -> bool {
+        match self {
+            LightLink::Only(ids) => object.is_some_and(|o| ids.contains(&o)),
+            LightLink::Except(ids) => !object.is_some_and(|o| ids.contains(&o)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    #[test]
+    fn inverse_square() {
+        let light = PointLight {
+            position: Vec3 {
+                x: 0.0,
+                y: 2.0,
+                z: 0.0,
+            },
+            intensity: Vec3::splat(8.0),
+            profile: None,
+        };
+        let s = light.sample_li(Vec3::zero(), &mut Rng::new(1)).unwrap();
+        assert_eq!(s.radiance, Vec3::splat(2.0));
+        assert_eq!(s.dist, 2.0);
+        assert_eq!(s.wi.y, 1.0);
+
+        assert!(light.sample_li(light.position, &mut Rng::new(1)).is_none());
+    }
+
+    #[test]
+    fn directional() {
+        let sun = DirectionalLight {
+            direction: Vec3 {
+                x: 0.0,
+                y: 3.0,
+                z: 4.0,
+            },
+            irradiance: Vec3::splat(2.0),
+        };
+        // Infinitely far away, so the same from every point
+        for &p in [Vec3::zero(), Vec3::splat(100.0)].iter() {
+            let s = sun.sample_li(p, &mut Rng::new(1)).unwrap();
+            assert_eq!(s.radiance, Vec3::splat(2.0));
+            assert!((s.wi.y - 0.6).abs() < 1e-12 && (s.wi.z - 0.8).abs() < 1e-12);
+            assert_eq!(s.dist, f64::INFINITY);
+            assert_eq!(s.pdf, 0.0);
+        }
+        assert!(sun.bounds().is_none());
+    }
+
+    #[test]
+    fn spot_falloff() {
+        let spot = SpotLight {
+            position: Vec3::zero(),
+            direction: Vec3 {
+                x: 0.0,
+                y: -1.0,
+                z: 0.0,
+            },
+            intensity: Vec3::splat(1.0),
+            inner_angle: 20.0,
+            outer_angle: 30.0,
+        };
+        let at = |deg: f64| {
+            let t = deg.to_radians();
+            spot.falloff(Vec3 {
+                x: t.sin(),
+                y: -t.cos(),
+                z: 0.0,
+            })
+        };
+        assert_eq!(at(0.0), 1.0);
+        assert_eq!(at(19.0), 1.0);
+        assert!(at(25.0) > 0.0 && at(25.0) < 1.0);
+        assert!(at(22.0) > at(28.0));
+        assert_eq!(at(31.0), 0.0);
+
+        // Points outside the cone get no light
+        let beside = Vec3 {
+            x: 5.0,
+            y: -1.0,
+            z: 0.0,
+        };
+        assert!(spot.sample_li(beside, &mut Rng::new(1)).is_none());
+        assert!(spot.sample_li(spot.position, &mut Rng::new(1)).is_none());
+    }
+
+    #[test]
+    fn linking() {
+        let only = LightLink::Only(vec![1, 3]);
+        assert!(only.reaches(Some(3)));
+        assert!(!only.reaches(Some(2)));
+        assert!(!only.reaches(None));
+
+        let except = LightLink::Except(vec![1]);
+        assert!(!except.reaches(Some(1)));
+        assert!(except.reaches(Some(2)));
+        assert!(except.reaches(None));
+    }
+}
@@ -0,0 +1,434 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use super::Light;
+use crate::geometry::Vec3;
+use crate::hittable::AABB;
+
+/// Where a light, or a group of lights, emits from and towards, for
+/// estimating how much it contributes to a point
+#[derive(Debug, Clone, Copy)]
+pub struct LightBounds {
+    pub bounds: AABB,
+    /// Total emitted power, as luminance
+    pub power: f64,
+    /// Center of the cone of directions the emitters face
+    pub axis: Vec3,
+    /// Cosine of the cone's half angle, -1 for lights facing every direction
+    pub cos_theta_o: f64,
+    /// Cosine of how far past the facing directions light is still emitted
+    pub cos_theta_e: f64,
+    /// Emits on both sides of the facing directions
+    pub two_sided: bool,
+}
+
+impl LightBounds {
+    /// Bounds of a light at a single position emitting in every direction
+    pub fn point(p: Vec3, power: f64) -> Self {
+        Self {
+            bounds: AABB { min: p, max: p },
+            power,
+            axis: Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: 1.0,
+            },
+            cos_theta_o: -1.0,
+            cos_theta_e: 0.0,
+            two_sided: false,
+        }
+    }
+
+    pub fn union(a: Self, b: Self) -> Self {
+        if a.power == 0.0 {
+            return b;
+        }
+        if b.power == 0.0 {
+            return a;
+        }
+
+        let (axis, cos_theta_o) = cone_union(a.axis, a.cos_theta_o, b.axis, b.cos_theta_o);
+        Self {
+            bounds: AABB::grow(a.bounds, b.bounds),
+            power: a.power + b.power,
+            axis,
+            cos_theta_o,
+            cos_theta_e: a.cos_theta_e.min(b.cos_theta_e),
+            two_sided: a.two_sided || b.two_sided,
+        }
+    }
+
+    fn centroid(&self) -> Vec3 {
+        0.5 * (self.bounds.min + self.bounds.max)
+    }
+
+    /// Conservative estimate of the light reaching `p` on a surface facing `n`.
+    /// Follows the light BVH of Conty Estevez and Kulla, 2018.
+    pub fn importance(&self, p: Vec3, n: Vec3) -> f64 {
+        let pc = self.centroid();
+        let radius = (self.bounds.max - pc).len();
+        let dist_sq = (p - pc).len_sq().max(radius).max(1e-8);
+
+        let wi = (p - pc).unit();
+        let mut cos_theta_w = Vec3::dot(self.axis, wi);
+        if self.two_sided {
+            cos_theta_w = cos_theta_w.abs();
+        }
+        let sin_theta_w = safe_sqrt(1.0 - cos_theta_w * cos_theta_w);
+
+        // Half angle of the cone of directions the bounds cover as seen from p
+        let cos_theta_b = if dist_sq < radius * radius {
+            -1.0
+        } else {
+            safe_sqrt(1.0 - radius * radius / dist_sq)
+        };
+        let sin_theta_b = safe_sqrt(1.0 - cos_theta_b * cos_theta_b);
+
+        // Smallest angle between the facing directions and p
+        let sin_theta_o = safe_sqrt(1.0 - self.cos_theta_o * self.cos_theta_o);
+        let (sin_theta_x, cos_theta_x) =
+            sub_clamped(sin_theta_w, cos_theta_w, sin_theta_o, self.cos_theta_o);
+        let (_, cos_theta_p) = sub_clamped(sin_theta_x, cos_theta_x, sin_theta_b, cos_theta_b);
+        if cos_theta_p <= self.cos_theta_e {
+            return 0.0;
+        }
+
+        let mut importance = self.power * cos_theta_p / dist_sq;
+        if !n.near_zero() {
+            let cos_theta_i = Vec3::dot(wi, n).abs();
+            let sin_theta_i = safe_sqrt(1.0 - cos_theta_i * cos_theta_i);
+            importance *= sub_clamped(sin_theta_i, cos_theta_i, sin_theta_b, cos_theta_b).1;
+        }
+        importance.max(0.0)
+    }
+}
+
+fn safe_sqrt(x: f64) -> f64 {
+    x.max(0.0).sqrt()
+}
+
+/// Sine and cosine of the angle `a` minus `b`, clamped at zero
+fn sub_clamped(sin_a: f64, cos_a: f64, sin_b: f64, cos_b: f64) -> (f64, f64) {
+    if cos_a > cos_b {
+        (0.0, 1.0)
+    } else {
+        (sin_a * cos_b - cos_a * sin_b, cos_a * cos_b + sin_a * sin_b)
+    }
+}
+
+/// Smallest cone containing both cones given by their axis and cosine of their half angle
+fn cone_union(wa: Vec3, cos_a: f64, wb: Vec3, cos_b: f64) -> (Vec3, f64) {
+    let theta_a = cos_a.clamp(-1.0, 1.0).acos();
+    let theta_b = cos_b.clamp(-1.0, 1.0).acos();
+    let theta_d = Vec3::dot(wa, wb).clamp(-1.0, 1.0).acos();
+    if (theta_d + theta_b).min(PI) <= theta_a {
+        return (wa, cos_a);
+    }
+    if (theta_d + theta_a).min(PI) <= theta_b {
+        return (wb, cos_b);
+    }
+
+    let theta_o = 0.5 * (theta_a + theta_d + theta_b);
+    let rot_axis = Vec3::cross(wa, wb);
+    if theta_o >= PI || rot_axis.near_zero() {
+        return (wa, -1.0);
+    }
+    let w = wa.rotate_axis_angle(rot_axis.unit(), theta_o - theta_a);
+    (w, theta_o.cos())
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    bounds: LightBounds,
+    /// Light index for leaves, index of the second child for interior nodes.
+    /// The first child directly follows its parent.
+    index: usize,
+    leaf: bool,
+}
+
+/// Picks lights in proportion to an estimate of their contribution to a point,
+/// so scenes with many lights only sample the ones that matter there.
+///
+/// Bounded lights are organized in a binary tree walked from the root, each step
+/// choosing a child by its importance. Lights without bounds, like directional
+/// lights, are picked uniformly with the tree as one more option.
+#[derive(Debug, Clone, Default)]
+pub struct LightTree {
+    nodes: Vec<Node>,
+    infinite: Vec<usize>,
+    /// Path from the root to each light's leaf, one bit per level, and its length
+    trails: Vec<Option<(u64, u32)>>,
+}
+
+impl LightTree {
+    pub fn new(lights: &[Arc<dyn Light>]) -> Self {
+        let mut tree = Self {
+            nodes: Vec::new(),
+            infinite: Vec::new(),
+            trails: vec![None; lights.len()],
+        };
+
+        let mut bounded = Vec::new();
+        for (i, light) in lights.iter().enumerate() {
+            match light.bounds() {
+                Some(b) if b.power > 0.0 => bounded.push((i, b)),
+                Some(_) => {}
+                None => tree.infinite.push(i),
+            }
+        }
+        if !bounded.is_empty() {
+            tree.build(&mut bounded, 0, 0);
+        }
+        tree
+    }
+
+    fn build(&mut self, lights: &mut [(usize, LightBounds)], trail: u64, depth: u32) -> usize {
+        let node = self.nodes.len();
+        if lights.len() == 1 {
+            let (i, bounds) = lights[0];
+            self.nodes.push(Node {
+                bounds,
+                index: i,
+                leaf: true,
+            });
+            self.trails[i] = Some((trail, depth));
+            return node;
+        }
+
+        // Split at the median centroid along the widest axis
+        let centroids = lights.iter().fold(
+            AABB {
+                min: Vec3::splat(f64::INFINITY),
+                max: Vec3::splat(f64::NEG_INFINITY),
+            },
+            |b, (_, l)| {
+                let c = l.centroid();
+                AABB::grow(b, AABB { min: c, max: c })
+            },
+        );
+        let extent = centroids.max - centroids.min;
+        let axis = if extent.x > extent.y && extent.x > extent.z {
+            0
+        } else if extent.y > extent.z {
+            1
+        } else {
+            2
+        };
+        lights.sort_by(|a, b| a.1.centroid()[axis].total_cmp(&b.1.centroid()[axis]));
+        let mid = lights.len() / 2;
+
+        self.nodes.push(Node {
+            bounds: lights
+                .iter()
+                .map(|(_, b)| *b)
+                .reduce(LightBounds::union)
+                .unwrap(),
+            index: 0,
+            leaf: false,
+        });
+        let (first, second) = lights.split_at_mut(mid);
+        self.build(first, trail, depth + 1);
+        self.nodes[node].index = self.build(second, trail | 1 << depth, depth + 1);
+        node
+    }
+
+    fn infinite_probability(&self) -> f64 {
+        let options = self.infinite.len() + if self.nodes.is_empty() { 0 } else { 1 };
+        if options == 0 {
+            0.0
+        } else {
+            self.infinite.len() as f64 / options as f64
+        }
+    }
+
+    /// Picks a light for the point `p` on a surface facing `n`, returning its
+    /// index and the probability it was picked with. `n` is zero for points
+    /// not on a surface.
+    pub fn sample(&self, p: Vec3, n: Vec3, u: f64) -> Option<(usize, f64)> {
+        let p_infinite = self.infinite_probability();
+        if u < p_infinite {
+            let k = ((u / p_infinite * self.infinite.len() as f64) as usize)
+                .min(self.infinite.len() - 1);
+            return Some((self.infinite[k], p_infinite / self.infinite.len() as f64));
+        }
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut u = ((u - p_infinite) / (1.0 - p_infinite)).min(1.0 - f64::EPSILON);
+        let mut pmf = 1.0 - p_infinite;
+        let mut node = 0;
+        loop {
+            let current = &self.nodes[node];
+            if current.leaf {
+                if current.bounds.importance(p, n) > 0.0 {
+                    return Some((current.index, pmf));
+                }
+                return None;
+            }
+
+            let children = [node + 1, current.index];
+            let ci = [
+                self.nodes[children[0]].bounds.importance(p, n),
+                self.nodes[children[1]].bounds.importance(p, n),
+            ];
+            if ci[0] == 0.0 && ci[1] == 0.0 {
+                return None;
+            }
+
+            let p_first = ci[0] / (ci[0] + ci[1]);
+            if u < p_first {
+                u = (u / p_first).min(1.0 - f64::EPSILON);
+                pmf *= p_first;
+                node = children[0];
+            } else {
+                u = ((u - p_first) / (1.0 - p_first)).min(1.0 - f64::EPSILON);
+                pmf *= 1.0 - p_first;
+                node = children[1];
+            }
+        }
+    }
+
+    /// Probability `sample` picks the light at `index` for `p` and `n`
+    pub fn pmf(&self, p: Vec3, n: Vec3, index: usize) -> f64 {
+        let p_infinite = self.infinite_probability();
+        let (mut trail, depth) = match self.trails.get(index).copied().flatten() {
+            Some(t) => t,
+            None if self.infinite.contains(&index) => {
+                return p_infinite / self.infinite.len() as f64;
+            }
+            None => return 0.0,
+        };
+
+        let mut pmf = 1.0 - p_infinite;
+        let mut node = 0;
+        for _ in 0..depth {
+            let children = [node + 1, self.nodes[node].index];
+            let ci = [
+                self.nodes[children[0]].bounds.importance(p, n),
+                self.nodes[children[1]].bounds.importance(p, n),
+            ];
+            let side = (trail & 1) as usize;
+            if ci[side] == 0.0 {
+                return 0.0;
+            }
+            pmf *= ci[side] / (ci[0] + ci[1]);
+            node = children[side];
+            trail >>= 1;
+        }
+        // A root that is a leaf gets the same test `sample` gives it
+        if self.nodes[node].bounds.importance(p, n) == 0.0 {
+            return 0.0;
+        }
+        pmf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{DirectionalLight, PointLight, SpotLight};
+    use super::*;
+
+    fn point(x: f64, power: f64) -> Arc<dyn Light> {
+        Arc::new(PointLight {
+            position: Vec3 { x, y: 1.0, z: 0.0 },
+            intensity: Vec3::splat(power),
+            profile: None,
+        })
+    }
+
+    #[test]
+    fn importance() {
+        let n = Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        };
+        let near = LightBounds::point(Vec3::splat(1.0), 1.0);
+        let far = LightBounds::point(Vec3::splat(5.0), 1.0);
+        assert!(near.importance(Vec3::zero(), n) > far.importance(Vec3::zero(), n));
+
+        // Nothing below a spotlight pointing up
+        let spot = SpotLight {
+            position: Vec3::zero(),
+            direction: n,
+            intensity: Vec3::splat(1.0),
+            inner_angle: 20.0,
+            outer_angle: 30.0,
+        }
+        .bounds()
+        .unwrap();
+        assert!(spot.importance(3.0 * n, -n) > 0.0);
+        assert_eq!(spot.importance(-3.0 * n, n), 0.0);
+
+        // Cones grow to hold both
+        let (w, cos) = cone_union(n, 1.0, -n, 1.0);
+        assert_eq!(cos, -1.0);
+        assert!(!w.near_zero());
+        let x = Vec3 {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        let (w, cos) = cone_union(n, 1.0, x, 1.0);
+        assert!((cos - (PI / 4.0).cos()).abs() < 1e-9);
+        assert!((Vec3::dot(w, n) - cos).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sampling() {
+        let mut lights = (0..9)
+            .map(|i| point(i as f64, 1.0 + i as f64))
+            .collect::<Vec<_>>();
+        lights.push(Arc::new(DirectionalLight {
+            direction: Vec3::splat(1.0),
+            irradiance: Vec3::splat(1.0),
+        }));
+        let tree = LightTree::new(&lights);
+
+        let p = Vec3 {
+            x: 2.0,
+            y: 0.0,
+            z: 0.0,
+        };
+        let n = Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        };
+        let mut counts = vec![0; lights.len()];
+        let samples = 100_000;
+        for k in 0..samples {
+            let (i, pmf) = tree
+                .sample(p, n, (k as f64 + 0.5) / samples as f64)
+                .unwrap();
+            assert!((pmf - tree.pmf(p, n, i)).abs() < 1e-12);
+            counts[i] += 1;
+        }
+
+        let total: f64 = (0..lights.len()).map(|i| tree.pmf(p, n, i)).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert_eq!(tree.pmf(p, n, 9), 0.5);
+        for (i, &c) in counts.iter().enumerate() {
+            assert!((c as f64 / samples as f64 - tree.pmf(p, n, i)).abs() < 0.01);
+        }
+
+        // The closest light beats one further away with more power
+        assert!(tree.pmf(p, n, 2) > tree.pmf(p, n, 8));
+
+        assert!(LightTree::new(&[]).sample(p, n, 0.5).is_none());
+
+        // A single light facing away is neither sampled nor given a probability
+        let spot: Arc<dyn Light> = Arc::new(SpotLight {
+            position: Vec3::zero(),
+            direction: n,
+            intensity: Vec3::splat(1.0),
+            inner_angle: 20.0,
+            outer_angle: 30.0,
+        });
+        let tree = LightTree::new(&[spot]);
+        assert!(tree.sample(-3.0 * n, n, 0.5).is_none());
+        assert_eq!(tree.pmf(-3.0 * n, n, 0), 0.0);
+        assert_eq!(tree.pmf(3.0 * n, -n, 0), 1.0);
+    }
+}
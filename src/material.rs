@@ -1,17 +1,49 @@
 use std::clone::Clone;
+use std::f64::consts::PI;
 use std::fmt::Debug;
 use std::sync::Arc;
 
 use crate::geometry::{Ray, Vec3};
 use crate::hittable::Rec;
-use crate::rng::Rng;
-use crate::texture::{SolidColor, Texture};
+use crate::light::{EmissionProfile, Power};
+use crate::sampler::Sampler;
+use crate::spectrum;
+use crate::texture::{ScalarTexture, SolidColor, Texture};
+
+/// A direction picked by `Material::scatter`
+#[derive(Debug, Clone, Copy)]
+pub struct Scatter {
+    pub ray: Ray,
+    /// BSDF times cosine divided by the density of the ray
+    pub attenuation: Vec3,
+    /// Density the direction was picked with in solid angle, zero for
+    /// specular directions that light sampling can't find
+    pub pdf: f64,
+}
 
 pub trait Material: Sync + Send + Debug {
-    fn scatter(&self, r_in: Ray, rec: &Rec, rng: &mut Rng) -> Option<(Ray, Vec3)>;
-    fn emitted(&self, _u: f64, _v: f64, _p: Vec3) -> Vec3 {
+    fn scatter(&self, r_in: Ray, rec: &Rec, sampler: &mut dyn Sampler) -> Option<Scatter>;
+
+    /// Radiance leaving the hit point back along `r_in`
+    fn emitted(&self, _r_in: Ray, _rec: &Rec) -> Vec3 {
         Vec3::zero()
     }
+
+    /// BSDF times cosine for light arriving from the unit direction `wi`.
+    /// Materials that can only be sampled through `scatter` return zero.
+    fn eval(&self, _r_in: Ray, _rec: &Rec, _wi: Vec3) -> Vec3 {
+        Vec3::zero()
+    }
+
+    /// Density `scatter` picks the unit direction `wi` with, excluding specular directions
+    fn pdf(&self, _r_in: Ray, _rec: &Rec, _wi: Vec3) -> f64 {
+        0.0
+    }
+}
+
+/// Density of cosine weighted directions around `n`
+fn cosine_pdf(n: Vec3, wi: Vec3) -> f64 {
+    Vec3::dot(n, wi).max(0.0) / PI
 }
 
 #[derive(Debug, Clone)]
@@ -28,72 +60,240 @@ impl From<Vec3> for Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _r_in: Ray, rec: &Rec, rng: &mut Rng) -> Option<(Ray, Vec3)> {
-        let mut scatter_dir = rec.n + Vec3::random_unit_sphere(rng);
+    fn scatter(&self, _r_in: Ray, rec: &Rec, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        let mut scatter_dir = rec.n + Vec3::random_unit_vector(sampler);
 
         if scatter_dir.near_zero() {
             scatter_dir = rec.n;
         }
 
-        Some((
-            Ray {
+        Some(Scatter {
+            ray: Ray {
                 o: rec.p,
                 d: scatter_dir,
+                diff: None,
             },
-            self.albedo.value(rec.u, rec.v, rec.p),
-        ))
+            attenuation: self
+                .albedo
+                .value_filtered(rec.u, rec.v, rec.p, &rec.footprint),
+            pdf: cosine_pdf(rec.n, scatter_dir.unit()),
+        })
+    }
+
+    fn eval(&self, _r_in: Ray, rec: &Rec, wi: Vec3) -> Vec3 {
+        cosine_pdf(rec.n, wi)
+            * self
+                .albedo
+                .value_filtered(rec.u, rec.v, rec.p, &rec.footprint)
+    }
+
+    fn pdf(&self, _r_in: Ray, rec: &Rec, wi: Vec3) -> f64 {
+        cosine_pdf(rec.n, wi)
     }
 }
 
-#[derive(Clone, Debug, Default)]
+/// Rough diffuse reflector with back-scattering (Oren–Nayar).
+#[derive(Debug, Clone)]
+pub struct OrenNayar {
+    pub albedo: Arc<dyn Texture>,
+    /// Standard deviation of the facet slope angle in radians.
+    /// A sigma of zero reduces to `Lambertian`.
+    pub sigma: Arc<dyn ScalarTexture>,
+}
+
+impl OrenNayar {
+    /// The BRDF relative to a Lambertian one of the same albedo
+    fn weight(&self, r_in: Ray, rec: &Rec, wi: Vec3) -> f64 {
+        let sigma = self
+            .sigma
+            .value_filtered(rec.u, rec.v, rec.p, &rec.footprint);
+        let sigma2 = sigma * sigma;
+        let a = 1.0 - sigma2 / (2.0 * (sigma2 + 0.33));
+        let b = 0.45 * sigma2 / (sigma2 + 0.09);
+
+        let wo = -r_in.d.unit();
+        let cos_i = Vec3::dot(wi, rec.n).clamp(0.0, 1.0);
+        let cos_o = Vec3::dot(wo, rec.n).clamp(0.0, 1.0);
+        let sin_i = (1.0 - cos_i * cos_i).sqrt();
+        let sin_o = (1.0 - cos_o * cos_o).sqrt();
+
+        // Cosine of the azimuthal angle between the two directions
+        let ti = wi - cos_i * rec.n;
+        let to = wo - cos_o * rec.n;
+        let cos_phi = if ti.near_zero() || to.near_zero() {
+            0.0
+        } else {
+            Vec3::dot(ti.unit(), to.unit()).max(0.0)
+        };
+
+        // alpha = max(theta_i, theta_o), beta = min(theta_i, theta_o)
+        let (sin_alpha, tan_beta) = if cos_i > cos_o {
+            (sin_o, sin_i / cos_i)
+        } else {
+            (sin_i, sin_o / cos_o.max(1e-8))
+        };
+
+        a + b * cos_phi * sin_alpha * tan_beta
+    }
+}
+
+impl Material for OrenNayar {
+    fn scatter(&self, r_in: Ray, rec: &Rec, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        let mut scatter_dir = rec.n + Vec3::random_unit_vector(sampler);
+
+        if scatter_dir.near_zero() {
+            scatter_dir = rec.n;
+        }
+
+        // Cosine-weighted sampling cancels the cosine and 1/pi of the BRDF
+        let wi = scatter_dir.unit();
+        Some(Scatter {
+            ray: Ray {
+                o: rec.p,
+                d: scatter_dir,
+                diff: None,
+            },
+            attenuation: self.weight(r_in, rec, wi)
+                * self
+                    .albedo
+                    .value_filtered(rec.u, rec.v, rec.p, &rec.footprint),
+            pdf: cosine_pdf(rec.n, wi),
+        })
+    }
+
+    fn eval(&self, r_in: Ray, rec: &Rec, wi: Vec3) -> Vec3 {
+        if Vec3::dot(wi, rec.n) <= 0.0 {
+            return Vec3::zero();
+        }
+        cosine_pdf(rec.n, wi)
+            * self.weight(r_in, rec, wi)
+            * self
+                .albedo
+                .value_filtered(rec.u, rec.v, rec.p, &rec.footprint)
+    }
+
+    fn pdf(&self, _r_in: Ray, rec: &Rec, wi: Vec3) -> f64 {
+        cosine_pdf(rec.n, wi)
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Metal {
     pub albedo: Vec3,
-    pub fuzz: f64,
+    pub fuzz: Arc<dyn ScalarTexture>,
+}
+
+impl Default for Metal {
+    fn default() -> Self {
+        Self {
+            albedo: Vec3::zero(),
+            fuzz: Arc::new(0.0),
+        }
+    }
+}
+
+impl Metal {
+    fn fuzz(&self, rec: &Rec) -> f64 {
+        self.fuzz
+            .value_filtered(rec.u, rec.v, rec.p, &rec.footprint)
+            .max(0.0)
+    }
+
+    /// Density of the direction of the mirror direction plus a point picked
+    /// uniformly on a sphere of radius `fuzz`, zero for a perfect mirror
+    fn lobe_pdf(r_in: Ray, rec: &Rec, fuzz: f64, wi: Vec3) -> f64 {
+        if fuzz <= 0.0 || Vec3::dot(wi, rec.n) <= 0.0 {
+            return 0.0;
+        }
+        let reflected = Vec3::reflect(r_in.d.unit(), rec.n);
+
+        // The ray along `wi` crosses the sphere at c ± s, each crossing
+        // seeing the sphere's uniform area density foreshortened by s / fuzz
+        let c = Vec3::dot(wi, reflected);
+        let disc = c * c - 1.0 + fuzz * fuzz;
+        if disc <= 0.0 {
+            return 0.0;
+        }
+        let s = disc.sqrt();
+        [c - s, c + s]
+            .iter()
+            .filter(|&&t| t > 0.0)
+            .map(|t| t * t)
+            .sum::<f64>()
+            / (4.0 * PI * fuzz * s)
+    }
 }
 
 impl Material for Metal {
-    fn scatter(&self, r_in: Ray, rec: &Rec, rng: &mut Rng) -> Option<(Ray, Vec3)> {
+    fn scatter(&self, r_in: Ray, rec: &Rec, sampler: &mut dyn Sampler) -> Option<Scatter> {
         let reflected = Vec3::reflect(r_in.d.unit(), rec.n);
+        let fuzz = self.fuzz(rec);
 
         let scattered = Ray {
             o: rec.p,
-            d: reflected + self.fuzz * Vec3::random_unit_sphere(rng),
+            d: reflected + fuzz * Vec3::random_unit_vector(sampler),
+            diff: None,
         };
 
         return if Vec3::dot(scattered.d, rec.n) > 0.0 {
-            Some((scattered, self.albedo))
+            Some(Scatter {
+                ray: scattered,
+                attenuation: self.albedo,
+                pdf: Self::lobe_pdf(r_in, rec, fuzz, scattered.d.unit()),
+            })
         } else {
             None
         };
     }
+
+    /// Directions ending below the surface are absorbed, so the lobe is its
+    /// own density scaled by the albedo
+    fn eval(&self, r_in: Ray, rec: &Rec, wi: Vec3) -> Vec3 {
+        Self::lobe_pdf(r_in, rec, self.fuzz(rec), wi) * self.albedo
+    }
+
+    fn pdf(&self, r_in: Ray, rec: &Rec, wi: Vec3) -> f64 {
+        Self::lobe_pdf(r_in, rec, self.fuzz(rec), wi)
+    }
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct Dielectric {
-    pub ir: f64,
+    pub ir: Arc<dyn ScalarTexture>,
+}
+
+impl Default for Dielectric {
+    fn default() -> Self {
+        Self { ir: Arc::new(0.0) }
+    }
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, r_in: Ray, rec: &Rec, rng: &mut Rng) -> Option<(Ray, Vec3)> {
-        let refraction_ratio = if rec.front_face {
-            1.0 / self.ir
-        } else {
-            self.ir
-        };
+    fn scatter(&self, r_in: Ray, rec: &Rec, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        let ir = self.ir.value_filtered(rec.u, rec.v, rec.p, &rec.footprint);
+        let refraction_ratio = if rec.front_face { 1.0 / ir } else { ir };
 
         let unit_dir = r_in.d.unit();
         let cos_theta = Vec3::dot(-unit_dir, rec.n).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let angle_criteria = reflectance(cos_theta, refraction_ratio) > rng.gen();
+        let angle_criteria = reflectance(cos_theta, refraction_ratio) > sampler.get_1d();
         let dir = if cannot_refract || angle_criteria {
             Vec3::reflect(unit_dir, rec.n)
         } else {
             Vec3::refract(unit_dir, rec.n, refraction_ratio)
         };
 
-        Some((Ray { o: rec.p, d: dir }, Vec3::splat(1.0)))
+        Some(Scatter {
+            ray: Ray {
+                o: rec.p,
+                d: dir,
+                diff: None,
+            },
+            attenuation: Vec3::splat(1.0),
+            pdf: 0.0,
+        })
     }
 }
 
@@ -103,17 +303,570 @@ fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
     r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
 }
 
+/// Emits `emit` evenly in all directions, or shaped by a photometric profile.
+/// A profile is spread over its `area` so the surface emits the profile's intensity,
+/// with `emit` tinting it.
 #[derive(Clone, Debug)]
 pub struct DiffuseLight {
     pub emit: Arc<dyn Texture>,
+    pub profile: Option<EmissionProfile>,
+    /// Emits from the back face too, otherwise only along the outward normal
+    pub two_sided: bool,
+}
+
+impl DiffuseLight {
+    /// Emitter of `power` spread evenly over a surface of `area`, colored like a
+    /// blackbody at `kelvin`
+    pub fn from_power(power: Power, kelvin: f64, area: f64, two_sided: bool) -> Self {
+        let sides = if two_sided { 2.0 } else { 1.0 };
+        let radiance = power.watts() / (PI * area * sides);
+        Self {
+            emit: Arc::new(SolidColor {
+                color_value: radiance * spectrum::blackbody_rgb(kelvin),
+            }),
+            profile: None,
+            two_sided,
+        }
+    }
 }
 
 impl Material for DiffuseLight {
-    fn scatter(&self, _r_in: Ray, _rec: &Rec, _rng: &mut Rng) -> Option<(Ray, Vec3)> {
+    fn scatter(&self, _r_in: Ray, _rec: &Rec, _sampler: &mut dyn Sampler) -> Option<Scatter> {
         None
     }
 
-    fn emitted(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
-        self.emit.value(u, v, p)
+    fn emitted(&self, r_in: Ray, rec: &Rec) -> Vec3 {
+        if !self.two_sided && !rec.front_face {
+            return Vec3::zero();
+        }
+
+        let emit = self.emit.value(rec.u, rec.v, rec.p);
+        match &self.profile {
+            Some(profile) => {
+                // Radiance whose projected area gives the profile's intensity
+                let w = -r_in.d.unit();
+                let cos_theta = Vec3::dot(w, rec.n).abs().max(1e-3);
+                profile.intensity(w) / (profile.area * cos_theta) * emit
+            }
+            None => emit,
+        }
+    }
+}
+
+/// Stochastically blends two materials, a `weight` of 0 picks `a` and 1 picks `b`.
+#[derive(Clone, Debug)]
+pub struct Mix {
+    pub a: Arc<dyn Material>,
+    pub b: Arc<dyn Material>,
+    pub weight: Arc<dyn ScalarTexture>,
+}
+
+impl Mix {
+    fn weight(&self, rec: &Rec) -> f64 {
+        self.weight
+            .value_filtered(rec.u, rec.v, rec.p, &rec.footprint)
+            .clamp(0.0, 1.0)
+    }
+}
+
+impl Material for Mix {
+    fn scatter(&self, r_in: Ray, rec: &Rec, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        let mut s = if sampler.get_1d() < self.weight(rec) {
+            self.b.scatter(r_in, rec, sampler)?
+        } else {
+            self.a.scatter(r_in, rec, sampler)?
+        };
+
+        // Either material could have picked a non-specular direction
+        if s.pdf > 0.0 {
+            s.pdf = self.pdf(r_in, rec, s.ray.d.unit());
+        }
+        Some(s)
+    }
+
+    fn eval(&self, r_in: Ray, rec: &Rec, wi: Vec3) -> Vec3 {
+        let w = self.weight(rec);
+        (1.0 - w) * self.a.eval(r_in, rec, wi) + w * self.b.eval(r_in, rec, wi)
+    }
+
+    fn pdf(&self, r_in: Ray, rec: &Rec, wi: Vec3) -> f64 {
+        let w = self.weight(rec);
+        (1.0 - w) * self.a.pdf(r_in, rec, wi) + w * self.b.pdf(r_in, rec, wi)
+    }
+
+    fn emitted(&self, r_in: Ray, rec: &Rec) -> Vec3 {
+        let w = self.weight(rec);
+        (1.0 - w) * self.a.emitted(r_in, rec) + w * self.b.emitted(r_in, rec)
+    }
+}
+
+/// A smooth dielectric clear coat layered over any base material.
+#[derive(Clone, Debug)]
+pub struct Coated {
+    pub base: Arc<dyn Material>,
+    /// Index of refraction of the coat
+    pub ir: Arc<dyn ScalarTexture>,
+}
+
+impl Coated {
+    /// Probability of reflecting off the coat, its Fresnel reflectance
+    fn coat_probability(&self, r_in: Ray, rec: &Rec) -> f64 {
+        // Rays leaving the object have already passed through the coat
+        if !rec.front_face {
+            return 0.0;
+        }
+
+        let cos_theta = Vec3::dot(-r_in.d.unit(), rec.n).min(1.0);
+        let ir = self.ir.value_filtered(rec.u, rec.v, rec.p, &rec.footprint);
+        reflectance(cos_theta, 1.0 / ir)
+    }
+}
+
+impl Material for Coated {
+    fn scatter(&self, r_in: Ray, rec: &Rec, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        // Pick the coat with probability equal to its Fresnel reflectance,
+        // otherwise the light makes it through to the base
+        let coat = self.coat_probability(r_in, rec);
+        if coat > sampler.get_1d() {
+            Some(Scatter {
+                ray: Ray {
+                    o: rec.p,
+                    d: Vec3::reflect(r_in.d.unit(), rec.n),
+                    diff: None,
+                },
+                attenuation: Vec3::splat(1.0),
+                pdf: 0.0,
+            })
+        } else {
+            let mut s = self.base.scatter(r_in, rec, sampler)?;
+            s.pdf *= 1.0 - coat;
+            Some(s)
+        }
+    }
+
+    fn eval(&self, r_in: Ray, rec: &Rec, wi: Vec3) -> Vec3 {
+        (1.0 - self.coat_probability(r_in, rec)) * self.base.eval(r_in, rec, wi)
+    }
+
+    fn pdf(&self, r_in: Ray, rec: &Rec, wi: Vec3) -> f64 {
+        (1.0 - self.coat_probability(r_in, rec)) * self.base.pdf(r_in, rec, wi)
+    }
+
+    fn emitted(&self, r_in: Ray, rec: &Rec) -> Vec3 {
+        self.base.emitted(r_in, rec)
+    }
+}
+
+/// Perturbs the shading normal of `base` with a tangent space normal map.
+/// Colors are decoded from [0, 1] to [-1, 1], z pointing along the surface normal.
+#[derive(Clone, Debug)]
+pub struct NormalMap {
+    pub base: Arc<dyn Material>,
+    pub map: Arc<dyn Texture>,
+}
+
+impl NormalMap {
+    fn shade(&self, rec: &Rec) -> Rec {
+        let m = 2.0 * self.map.value_filtered(rec.u, rec.v, rec.p, &rec.footprint) - 1.0;
+        let (t, b, n) = rec.frame();
+        let ns = (m.x * t + m.y * b + m.z * n).unit();
+
+        Rec {
+            n: if ns.near_zero() { rec.n } else { ns },
+            ..*rec
+        }
+    }
+}
+
+impl Material for NormalMap {
+    fn scatter(&self, r_in: Ray, rec: &Rec, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        self.base.scatter(r_in, &self.shade(rec), sampler)
+    }
+
+    fn eval(&self, r_in: Ray, rec: &Rec, wi: Vec3) -> Vec3 {
+        self.base.eval(r_in, &self.shade(rec), wi)
+    }
+
+    fn pdf(&self, r_in: Ray, rec: &Rec, wi: Vec3) -> f64 {
+        self.base.pdf(r_in, &self.shade(rec), wi)
+    }
+
+    fn emitted(&self, r_in: Ray, rec: &Rec) -> Vec3 {
+        self.base.emitted(r_in, rec)
+    }
+}
+
+/// Perturbs the shading normal of `base` using the gradient of a height field.
+/// Height is multiplied by `scale`.
+#[derive(Clone, Debug)]
+pub struct BumpMap {
+    pub base: Arc<dyn Material>,
+    pub height: Arc<dyn ScalarTexture>,
+    pub scale: f64,
+}
+
+impl BumpMap {
+    fn shade(&self, rec: &Rec) -> Rec {
+        const DELTA: f64 = 0.0005;
+
+        let h = |u: f64, v: f64, p: Vec3| {
+            self.scale * self.height.value_filtered(u, v, p, &rec.footprint)
+        };
+        let (t, b, n) = rec.frame();
+        // Primitives without tangents are bumped along an arbitrary unit frame
+        let (dpdu, dpdv) = if rec.dpdu.near_zero() || rec.dpdv.near_zero() {
+            (t, b)
+        } else {
+            (rec.dpdu, rec.dpdv)
+        };
+
+        // Differences over half the footprint, the filtered height is smooth below it
+        let d = (0.5 * rec.footprint.uv_width).max(DELTA);
+        let h0 = h(rec.u, rec.v, rec.p);
+        let dhdu = (h(rec.u + d, rec.v, rec.p + d * dpdu) - h0) / d;
+        let dhdv = (h(rec.u, rec.v + d, rec.p + d * dpdv) - h0) / d;
+
+        let mut ns = Vec3::cross(dpdu + dhdu * n, dpdv + dhdv * n).unit();
+        if Vec3::dot(ns, n) < 0.0 {
+            ns = -ns;
+        }
+
+        Rec { n: ns, ..*rec }
+    }
+}
+
+impl Material for BumpMap {
+    fn scatter(&self, r_in: Ray, rec: &Rec, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        self.base.scatter(r_in, &self.shade(rec), sampler)
+    }
+
+    fn eval(&self, r_in: Ray, rec: &Rec, wi: Vec3) -> Vec3 {
+        self.base.eval(r_in, &self.shade(rec), wi)
+    }
+
+    fn pdf(&self, r_in: Ray, rec: &Rec, wi: Vec3) -> f64 {
+        self.base.pdf(r_in, &self.shade(rec), wi)
+    }
+
+    fn emitted(&self, r_in: Ray, rec: &Rec) -> Vec3 {
+        self.base.emitted(r_in, rec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::LUMENS_PER_WATT;
+    use crate::rng::Rng;
+    use crate::texture::{
+        Channel, Footprint, ImageTexture, LinearGradient, Luminance, MipMap, ScalarChannel,
+        TextureSpace,
+    };
+
+    fn up() -> Rec {
+        Rec {
+            n: Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: 1.0,
+            },
+            front_face: true,
+            ..Rec::default()
+        }
+    }
+
+    /// Integral of `material`'s pdf over the sphere, by uniform directions
+    fn pdf_integral(material: &dyn Material, r_in: Ray, rng: &mut Rng) -> f64 {
+        let n = 200_000;
+        let rec = up();
+        let sum: f64 = (0..n)
+            .map(|_| material.pdf(r_in, &rec, Vec3::random_unit_vector(rng)))
+            .sum();
+        4.0 * PI * sum / n as f64
+    }
+
+    /// Fraction of `scatter` calls that pick a non-specular direction
+    fn scatter_fraction(material: &dyn Material, r_in: Ray, rng: &mut Rng) -> f64 {
+        let n = 200_000;
+        let rec = up();
+        let hits = (0..n)
+            .filter(|_| {
+                material
+                    .scatter(r_in, &rec, rng)
+                    .is_some_and(|s| s.pdf > 0.0)
+            })
+            .count();
+        hits as f64 / n as f64
+    }
+
+    fn incoming(x: f64) -> Ray {
+        Ray {
+            o: Vec3 {
+                x: -x,
+                y: 0.0,
+                z: 1.0,
+            },
+            d: Vec3 { x, y: 0.0, z: -1.0 },
+            diff: None,
+        }
+    }
+
+    fn lambertian() -> Arc<dyn Material> {
+        Arc::new(Lambertian::from(Vec3 {
+            x: 0.8,
+            y: 0.5,
+            z: 0.2,
+        }))
+    }
+
+    #[test]
+    fn oren_nayar_smooth() {
+        let oren_nayar = OrenNayar {
+            albedo: Arc::new(SolidColor {
+                color_value: Vec3 {
+                    x: 0.8,
+                    y: 0.5,
+                    z: 0.2,
+                },
+            }),
+            sigma: Arc::new(0.0),
+        };
+        let lambertian = lambertian();
+        let rec = up();
+        let r_in = incoming(0.7);
+        let mut rng = Rng::new(2);
+        for _ in 0..100 {
+            let wi = Vec3::random_unit_vector(&mut rng);
+            let expected = lambertian.eval(r_in, &rec, wi);
+            assert!((oren_nayar.eval(r_in, &rec, wi) - expected).len() < 1e-12);
+            assert_eq!(
+                oren_nayar.pdf(r_in, &rec, wi),
+                lambertian.pdf(r_in, &rec, wi)
+            );
+        }
+
+        let a = oren_nayar.scatter(r_in, &rec, &mut Rng::new(3)).unwrap();
+        let b = lambertian.scatter(r_in, &rec, &mut Rng::new(3)).unwrap();
+        assert_eq!(a.ray.d, b.ray.d);
+        assert!((a.attenuation - b.attenuation).len() < 1e-12);
+        assert_eq!(a.pdf, b.pdf);
+    }
+
+    #[test]
+    fn layered_pdf() {
+        let mut rng = Rng::new(4);
+        let rec = up();
+        let r_in = incoming(0.7);
+
+        // The mirror half of the mix can't be found by light sampling
+        let mix = Mix {
+            a: lambertian(),
+            b: Arc::new(Metal {
+                albedo: Vec3::splat(0.9),
+                fuzz: Arc::new(0.0),
+            }),
+            weight: Arc::new(0.3),
+        };
+        let integral = pdf_integral(&mix, r_in, &mut rng);
+        assert!((integral - 0.7).abs() < 0.01, "{}", integral);
+
+        // Neither can the reflection off the coat
+        let coated = Coated {
+            base: lambertian(),
+            ir: Arc::new(1.5),
+        };
+        let coat = coated.coat_probability(r_in, &rec);
+        assert!(coat > 0.0);
+        let integral = pdf_integral(&coated, r_in, &mut rng);
+        assert!(
+            (integral - (1.0 - coat)).abs() < 0.01,
+            "{} {}",
+            integral,
+            coat
+        );
+    }
+
+    #[test]
+    fn shading_normals() {
+        let rec = Rec {
+            dpdu: Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            dpdv: Vec3 {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            },
+            ..up()
+        };
+        let normal_map = |color: Vec3| NormalMap {
+            base: lambertian(),
+            map: Arc::new(SolidColor { color_value: color }),
+        };
+
+        // A flat map keeps the normal, one pointing along u tilts it there
+        let flat = normal_map(Vec3 {
+            x: 0.5,
+            y: 0.5,
+            z: 1.0,
+        });
+        assert!((flat.shade(&rec).n - rec.n).len() < 1e-12);
+        let tilted = normal_map(Vec3 {
+            x: 1.0,
+            y: 0.5,
+            z: 1.0,
+        })
+        .shade(&rec)
+        .n;
+        assert!(tilted.x > 0.0 && tilted.y.abs() < 1e-12 && tilted.z > 0.0);
+
+        // Stripes tilting either way along u flatten out under a wide footprint
+        let texels = (0..4)
+            .map(|i| Vec3 {
+                x: (i % 2) as f64,
+                y: 0.5,
+                z: 1.0,
+            })
+            .collect::<Vec<_>>();
+        let stripes = NormalMap {
+            base: lambertian(),
+            map: Arc::new(MipMap::new(ImageTexture::new(4, 1, texels))),
+        };
+        let sharp = Rec { u: 0.375, ..rec };
+        assert!(stripes.shade(&sharp).n.x > 0.5);
+        let wide = Rec {
+            footprint: Footprint {
+                width: 1.0,
+                uv_width: 1.0,
+            },
+            ..sharp
+        };
+        assert!((stripes.shade(&wide).n - rec.n).len() < 1e-9);
+
+        // A height rising along u tilts the normal back against u
+        let bump = |height: Arc<dyn ScalarTexture>| BumpMap {
+            base: lambertian(),
+            height,
+            scale: 0.5,
+        };
+        assert!((bump(Arc::new(0.3)).shade(&rec).n - rec.n).len() < 1e-12);
+        let ramp = Luminance {
+            texture: Arc::new(LinearGradient {
+                space: TextureSpace::Uv,
+                start: Vec3::zero(),
+                end: rec.dpdu,
+                from: Vec3::zero(),
+                to: Vec3::splat(1.0),
+            }),
+        };
+        let n = bump(Arc::new(ramp)).shade(&Rec { u: 0.5, ..rec }).n;
+        let expected = Vec3 {
+            x: -0.5,
+            y: 0.0,
+            z: 1.0,
+        }
+        .unit();
+        assert!((n - expected).len() < 1e-6, "{:?}", n);
+    }
+
+    #[test]
+    fn textured_parameters() {
+        // Fuzz rising from nothing at u = 0, read from the red channel
+        let metal = Metal {
+            albedo: Vec3::splat(0.9),
+            fuzz: Arc::new(ScalarChannel {
+                texture: Arc::new(LinearGradient {
+                    space: TextureSpace::Uv,
+                    start: Vec3::zero(),
+                    end: Vec3 {
+                        x: 1.0,
+                        y: 0.0,
+                        z: 0.0,
+                    },
+                    from: Vec3::zero(),
+                    to: Vec3 {
+                        x: 0.5,
+                        y: 0.0,
+                        z: 0.0,
+                    },
+                }),
+                channel: Channel::R,
+            }),
+        };
+        let mut rng = Rng::new(6);
+        let r_in = incoming(0.2);
+        let mirror = metal.scatter(r_in, &up(), &mut rng).unwrap();
+        assert_eq!(mirror.pdf, 0.0);
+        let rough = Rec { u: 1.0, ..up() };
+        assert!(metal.scatter(r_in, &rough, &mut rng).unwrap().pdf > 0.0);
+        assert_eq!(metal.fuzz(&Rec { u: 0.5, ..up() }), 0.25);
+    }
+
+    #[test]
+    fn light_power() {
+        let rec = up();
+        let r_in = incoming(0.3);
+        let radiance = |light: &DiffuseLight, front_face: bool| {
+            light.emitted(r_in, &Rec { front_face, ..rec }).luminance()
+        };
+
+        // 2 pi watts over 2 square units facing one way is a luminance of 1
+        let one_sided = DiffuseLight::from_power(Power::Watts(2.0 * PI), 6500.0, 2.0, false);
+        assert!((radiance(&one_sided, true) - 1.0).abs() < 1e-6);
+        assert_eq!(radiance(&one_sided, false), 0.0);
+
+        // Lumens are the same power, and two sides share it
+        let lumens = Power::Lumens(2.0 * PI * LUMENS_PER_WATT);
+        let two_sided = DiffuseLight::from_power(lumens, 6500.0, 2.0, true);
+        assert!((radiance(&two_sided, true) - 0.5).abs() < 1e-6);
+        assert!((radiance(&two_sided, false) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn fuzzy_metal() {
+        let mut rng = Rng::new(5);
+        let metal = Metal {
+            albedo: Vec3 {
+                x: 0.9,
+                y: 0.6,
+                z: 0.3,
+            },
+            fuzz: Arc::new(0.5),
+        };
+        let rec = up();
+
+        // Head on the whole ball is above the surface, at a grazing angle part
+        // of it is absorbed
+        for &x in [0.0, 3.0].iter() {
+            let r_in = incoming(x);
+            let integral = pdf_integral(&metal, r_in, &mut rng);
+            let fraction = scatter_fraction(&metal, r_in, &mut rng);
+            assert!(
+                (integral - fraction).abs() < 0.03,
+                "{} {}",
+                integral,
+                fraction
+            );
+
+            let s = metal.scatter(r_in, &rec, &mut rng).unwrap();
+            let wi = s.ray.d.unit();
+            assert!((s.pdf - metal.pdf(r_in, &rec, wi)).abs() < 1e-12);
+            let eval = metal.eval(r_in, &rec, wi);
+            assert!((eval / s.pdf - metal.albedo).len() < 1e-9);
+        }
+        assert!(scatter_fraction(&metal, incoming(3.0), &mut rng) < 0.9);
+
+        let mirror = Metal {
+            fuzz: Arc::new(0.0),
+            ..metal
+        };
+        let s = mirror.scatter(incoming(0.5), &rec, &mut rng).unwrap();
+        assert_eq!(s.pdf, 0.0);
+        assert_eq!(
+            mirror.eval(incoming(0.5), &rec, s.ray.d.unit()),
+            Vec3::zero()
+        );
     }
 }
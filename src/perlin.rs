@@ -9,11 +9,21 @@ pub struct Perlin {
     perm_z: [usize; Self::POINT_COUNT],
 }
 
+impl Default for Perlin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Perlin {
     const POINT_COUNT: usize = 256;
 
     pub fn new() -> Self {
-        let mut rng = Rng::new(1234);
+        Self::with_seed(1234)
+    }
+
+    pub fn with_seed(seed: u32) -> Self {
+        let mut rng = Rng::new(seed.into());
 
         let mut ranvec = [Vec3::zero(); Self::POINT_COUNT];
         ranvec
@@ -113,4 +123,20 @@ impl Perlin {
 
         accum.abs()
     }
+
+    /// Fractal Brownian motion: `octaves` layers of noise, each `lacunarity`
+    /// times the frequency and `gain` times the amplitude of the previous one.
+    pub fn fbm(&self, p: Vec3, octaves: usize, lacunarity: f64, gain: f64) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = p;
+        let mut weight = 1.0;
+
+        for _ in 0..octaves {
+            accum += weight * self.noise(temp_p);
+            weight *= gain;
+            temp_p *= lacunarity;
+        }
+
+        accum
+    }
 }
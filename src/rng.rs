@@ -1,22 +1,54 @@
+//! PCG32 (O'Neill, "PCG: A Family of Simple Fast Space-Efficient Statistically
+//! Good Algorithms for Random Number Generation"). A 64 bit LCG whose output is
+//! permuted down to 32 bits. Each odd increment gives a different sequence, so
+//! any number of independent streams can be drawn from one seed.
+
+use crate::sampler::Sampler;
+
+const MULTIPLIER: u64 = 0x5851_f42d_4c95_7f2d;
+const DEFAULT_STREAM: u64 = 0xda3e_39cb_94b9_5bdb;
+
+#[derive(Debug, Clone)]
 pub struct Rng {
-    seed: u32,
+    state: u64,
+    /// Always odd, picks the stream
+    inc: u64,
 }
 
 impl Rng {
-    pub fn new(seed: u32) -> Self {
-        Self { seed }
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, DEFAULT_STREAM)
+    }
+
+    /// Generator for one of 2^63 independent streams of `seed`
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            inc: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    #[inline(always)]
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    #[inline(always)]
+    pub fn next_u64(&mut self) -> u64 {
+        ((self.next_u32() as u64) << 32) | self.next_u32() as u64
     }
 
+    /// Uniform in [0, 1) with 32 random bits
     #[inline(always)]
     pub fn gen(&mut self) -> f64 {
-        let mut x = self.seed;
-        x ^= x >> 13;
-        x ^= x << 17;
-        x ^= x >> 5;
-        self.seed = x;
-        let float_bits = (x & 0x007FFFFF) | 0x3F800000;
-        let float: f32 = unsafe { ::core::mem::transmute(float_bits) };
-        (float - 1.0) as f64
+        self.next_u32() as f64 / 4_294_967_296.0
     }
 
     #[inline(always)]
@@ -24,8 +56,136 @@ impl Rng {
         min + (max - min) * self.gen()
     }
 
-    #[inline(always)]
+    /// Uniform integer in `min..=max`, every value equally likely
     pub fn int(&mut self, min: i64, max: i64) -> i64 {
-        self.range(min as f64, (max + 1) as f64) as i64
+        let span = (max.wrapping_sub(min) as u64).wrapping_add(1);
+        if span == 0 {
+            // The full range of i64
+            return self.next_u64() as i64;
+        }
+        // Reject the values past the last whole multiple of `span`
+        let threshold = span.wrapping_neg() % span;
+        loop {
+            let x = self.next_u64();
+            if x >= threshold {
+                return min.wrapping_add((x % span) as i64);
+            }
+        }
+    }
+
+    /// Skips the next `delta` numbers in O(log delta), after Brown "Random
+    /// Number Generation with Arbitrary Strides"
+    pub fn advance(&mut self, mut delta: u64) {
+        let (mut mult, mut plus) = (MULTIPLIER, self.inc);
+        let (mut acc_mult, mut acc_plus) = (1u64, 0u64);
+        while delta > 0 {
+            if delta & 1 == 1 {
+                acc_mult = acc_mult.wrapping_mul(mult);
+                acc_plus = acc_plus.wrapping_mul(mult).wrapping_add(plus);
+            }
+            plus = mult.wrapping_add(1).wrapping_mul(plus);
+            mult = mult.wrapping_mul(mult);
+            delta >>= 1;
+        }
+        self.state = acc_mult.wrapping_mul(self.state).wrapping_add(acc_plus);
+    }
+
+    /// A new generator on a stream picked by this one, for handing to another task
+    pub fn split(&mut self) -> Self {
+        let seed = self.next_u64();
+        Self::with_stream(seed, self.next_u64())
+    }
+}
+
+/// Plain random numbers wherever a sampler is expected, ignoring pixels and dimensions
+impl Sampler for Rng {
+    fn start_pixel_sample(&mut self, _pixel: (usize, usize), _index: usize, _count: usize) {}
+
+    fn get_1d(&mut self) -> f64 {
+        self.gen()
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+/// SplitMix64 finalizer
+pub(crate) fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Mixes `values` into 64 well distributed bits
+pub(crate) fn hash(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(0x9e37_79b9_7f4a_7c15, |h, &v| mix(h ^ mix(v)))
+}
+
+/// `hash` of `values` as a number in [0, 1)
+pub(crate) fn hash_unit(values: &[u64]) -> f64 {
+    (hash(values) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference() {
+        // First outputs of the PCG reference pcg32-demo, seeded 42 on stream 54
+        let mut rng = Rng::with_stream(42, 54);
+        let expected = [
+            0xa15c_02b7,
+            0x7b47_f409,
+            0xba1d_3330,
+            0x83d2_f293,
+            0xbfa4_784b,
+            0xcbed_606e,
+        ];
+        for &e in expected.iter() {
+            assert_eq!(rng.next_u32(), e);
+        }
+    }
+
+    #[test]
+    fn streams() {
+        // A zero seed is as good as any other
+        let mut zero = Rng::new(0);
+        assert!((0..8).map(|_| zero.next_u32()).any(|x| x != 0));
+
+        let mut a = Rng::with_stream(7, 1);
+        let mut b = Rng::with_stream(7, 2);
+        assert!((0..8).all(|_| a.next_u32() != b.next_u32()));
+
+        let mut stepped = Rng::with_stream(7, 3);
+        let mut jumped = stepped.clone();
+        for _ in 0..1000 {
+            stepped.next_u32();
+        }
+        jumped.advance(1000);
+        assert_eq!(stepped.next_u32(), jumped.next_u32());
+
+        let mut child = stepped.split();
+        assert!((0..8).all(|_| child.next_u32() != stepped.next_u32()));
+    }
+
+    #[test]
+    fn int() {
+        let mut rng = Rng::new(3);
+        let mut counts = [0; 3];
+        for _ in 0..30000 {
+            let x = rng.int(-1, 1);
+            counts[(x + 1) as usize] += 1;
+        }
+        assert!(
+            counts.iter().all(|&c| (c - 10000i32).abs() < 400),
+            "{:?}",
+            counts
+        );
+        assert_eq!(rng.int(5, 5), 5);
+        rng.int(i64::MIN, i64::MAX);
     }
 }
@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use super::sobol::{owen_scramble, sobol_0, sobol_1};
+use super::{hash, mix, permutation_element, to_unit, wrap, Sampler};
+use crate::rng::Rng;
+
+/// Side of the tile `BlueNoiseSampler::new` generates
+const TILE_SIZE: usize = 64;
+
+/// A square of values in [0, 1), each appearing once, arranged so that close
+/// values are far apart and it repeats seamlessly. Generated with Ulichney's
+/// void-and-cluster method.
+#[derive(Debug, Clone)]
+pub struct BlueNoiseTile {
+    size: usize,
+    /// Order each pixel was filled in, row by row
+    rank: Vec<u32>,
+}
+
+impl BlueNoiseTile {
+    pub fn new(size: usize, seed: u64) -> Self {
+        let n = size * size;
+        let mut pattern = Pattern::new(size);
+
+        // A random tenth of the pixels, evened out by moving the tightest
+        // cluster into the largest void until that is where it came from
+        let mut rng = Rng::new(seed);
+        let initial = (n / 10).max(1);
+        let mut set = 0;
+        while set < initial {
+            let i = ((rng.gen() * n as f64) as usize).min(n - 1);
+            if !pattern.ones[i] {
+                pattern.toggle(i);
+                set += 1;
+            }
+        }
+        for _ in 0..n {
+            let cluster = pattern.tightest_cluster();
+            pattern.toggle(cluster);
+            let void = pattern.largest_void();
+            pattern.toggle(void);
+            if void == cluster {
+                break;
+            }
+        }
+
+        // Ranks below the initial pattern come from taking it apart cluster by
+        // cluster, the rest from filling voids. Past half full the largest void
+        // is also the tightest cluster of empty pixels, so that needs no switch.
+        let mut rank = vec![0; n];
+        let mut removing = pattern.clone();
+        for r in (0..initial).rev() {
+            let cluster = removing.tightest_cluster();
+            removing.toggle(cluster);
+            rank[cluster] = r as u32;
+        }
+        for r in initial..n {
+            let void = pattern.largest_void();
+            pattern.toggle(void);
+            rank[void] = r as u32;
+        }
+
+        Self { size, rank }
+    }
+
+    /// Value at a pixel, wrapping around the tile
+    pub fn value(&self, x: usize, y: usize) -> f64 {
+        let rank = self.rank[(y % self.size) * self.size + x % self.size];
+        (rank as f64 + 0.5) / self.rank.len() as f64
+    }
+}
+
+/// Pixels of a tile that are set, and how crowded the set pixels make each spot
+#[derive(Clone)]
+struct Pattern {
+    size: usize,
+    ones: Vec<bool>,
+    /// Set pixels around each pixel, weighted by a Gaussian of their distance
+    energy: Vec<f64>,
+    /// The Gaussian at each offset, wrapping around
+    kernel: Arc<[f64]>,
+}
+
+impl Pattern {
+    fn new(size: usize) -> Self {
+        let sigma = 1.5;
+        let kernel = (0..size * size)
+            .map(|i| {
+                let (dx, dy) = (i % size, i / size);
+                let dx = dx.min(size - dx) as f64;
+                let dy = dy.min(size - dy) as f64;
+                (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp()
+            })
+            .collect();
+        Self {
+            size,
+            ones: vec![false; size * size],
+            energy: vec![0.0; size * size],
+            kernel,
+        }
+    }
+
+    fn toggle(&mut self, i: usize) {
+        self.ones[i] = !self.ones[i];
+        let sign = if self.ones[i] { 1.0 } else { -1.0 };
+        let (x, y) = (i % self.size, i / self.size);
+        for (j, energy) in self.energy.iter_mut().enumerate() {
+            let dx = (j % self.size + self.size - x) % self.size;
+            let dy = (j / self.size + self.size - y) % self.size;
+            *energy += sign * self.kernel[dy * self.size + dx];
+        }
+    }
+
+    /// The most crowded set pixel
+    fn tightest_cluster(&self) -> usize {
+        self.extreme(true, |a, b| a > b)
+    }
+
+    /// The least crowded empty pixel
+    fn largest_void(&self) -> usize {
+        self.extreme(false, |a, b| a < b)
+    }
+
+    fn extreme(&self, set: bool, better: impl Fn(f64, f64) -> bool) -> usize {
+        let mut best = None;
+        for (i, &energy) in self.energy.iter().enumerate() {
+            if self.ones[i] == set && best.is_none_or(|b: usize| better(energy, self.energy[b])) {
+                best = Some(i);
+            }
+        }
+        best.unwrap()
+    }
+}
+
+/// Scrambled Sobol points shared by every pixel, shifted per pixel by a blue
+/// noise tile, as in Heitz and Belcour "Distributing Monte Carlo Errors as a
+/// Blue Noise in Screen Space". At a few samples per pixel the error of
+/// neighboring pixels is anticorrelated, which reads as fine grain instead of
+/// blotches. Changing the seed every frame of an animation moves the tile and
+/// the points.
+#[derive(Debug, Clone)]
+pub struct BlueNoiseSampler {
+    seed: u64,
+    tile: Arc<BlueNoiseTile>,
+    pixel: (usize, usize),
+    index: usize,
+    count: usize,
+    dimension: u64,
+}
+
+impl BlueNoiseSampler {
+    /// Generates a 64 by 64 tile, see `with_tile` to reuse one
+    pub fn new(seed: u64) -> Self {
+        Self::with_tile(Arc::new(BlueNoiseTile::new(TILE_SIZE, 1)), seed)
+    }
+
+    pub fn with_tile(tile: Arc<BlueNoiseTile>, seed: u64) -> Self {
+        Self {
+            seed,
+            tile,
+            pixel: (0, 0),
+            index: 0,
+            count: 1,
+            dimension: 0,
+        }
+    }
+
+    /// Shuffled index of the current sample, the same in every pixel, and the
+    /// hash to scramble and shift it with
+    fn next(&mut self, dimensions: u64) -> (u32, u64) {
+        let (index, round) = wrap(self.index, self.count);
+        let h = hash(&[self.dimension, round, self.seed]);
+        self.dimension += dimensions;
+        (
+            permutation_element(index, self.count as u32, h as u32),
+            mix(h),
+        )
+    }
+
+    /// Blue noise value of the current pixel, in a part of the tile picked by `h`
+    fn shift(&self, h: u64) -> f64 {
+        let size = self.tile.size;
+        self.tile.value(
+            self.pixel.0 + (h as usize) % size,
+            self.pixel.1 + ((h >> 32) as usize) % size,
+        )
+    }
+}
+
+/// Adds a shift to a sample, wrapping around
+fn rotate(x: f64, shift: f64) -> f64 {
+    let x = (x + shift).fract();
+    // Rounding can reach 1
+    if x < 1.0 {
+        x
+    } else {
+        0.0
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize, count: usize) {
+        self.pixel = pixel;
+        self.index = index;
+        self.count = count.max(1);
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (index, h) = self.next(1);
+        let x = to_unit(owen_scramble(sobol_0(index), h as u32));
+        rotate(x, self.shift(mix(h)))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (index, h) = self.next(2);
+        let x = to_unit(owen_scramble(sobol_0(index), h as u32));
+        let y = to_unit(owen_scramble(sobol_1(index), (h >> 32) as u32));
+        let h = mix(h);
+        (rotate(x, self.shift(h)), rotate(y, self.shift(mix(h))))
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile() {
+        let size = 16;
+        let tile = BlueNoiseTile::new(size, 3);
+        let mut ranks = tile.rank.clone();
+        ranks.sort_unstable();
+        assert!(ranks.iter().enumerate().all(|(i, &r)| r as usize == i));
+
+        // Neighbors differ by more than the 1/3 of white noise
+        let mut difference = 0.0;
+        for y in 0..size {
+            for x in 0..size {
+                let v = tile.value(x, y);
+                difference += (v - tile.value(x + 1, y)).abs() + (v - tile.value(x, y + 1)).abs();
+            }
+        }
+        difference /= (2 * size * size) as f64;
+        assert!(difference > 0.4, "{}", difference);
+    }
+}
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use super::{hash, to_unit, Sampler};
+
+/// Dimensions with a Halton base of their own, later ones are random
+const DIMENSIONS: usize = 256;
+
+/// The Halton sequence, the radical inverse of the sample index in a different
+/// prime base for every dimension. Each pixel shifts every dimension by a random
+/// offset, wrapping around, so neighboring pixels don't repeat the same pattern.
+#[derive(Debug, Clone)]
+pub struct HaltonSampler {
+    seed: u64,
+    primes: Arc<[u64]>,
+    pixel: (usize, usize),
+    index: usize,
+    dimension: usize,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        let mut primes = Vec::with_capacity(DIMENSIONS);
+        let mut n = 2;
+        while primes.len() < DIMENSIONS {
+            if primes
+                .iter()
+                .take_while(|&&p| p * p <= n)
+                .all(|&p| n % p != 0)
+            {
+                primes.push(n);
+            }
+            n += 1;
+        }
+        Self {
+            seed,
+            primes: primes.into(),
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+/// Mirrors the digits of `n` in `base` around the decimal point
+fn radical_inverse(base: u64, mut n: u64) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let (mut reversed, mut scale) = (0.0, inv_base);
+    while n > 0 {
+        reversed += (n % base) as f64 * scale;
+        n /= base;
+        scale *= inv_base;
+    }
+    reversed
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize, _count: usize) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let h = hash(&[
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            self.dimension as u64,
+            self.seed,
+        ]);
+        let offset = to_unit(h as u32);
+        let x = match self.primes.get(self.dimension) {
+            Some(&base) => radical_inverse(base, self.index as u64) + offset,
+            None => to_unit(hash(&[h, self.index as u64]) as u32),
+        };
+        self.dimension += 1;
+        let x = x.fract();
+        // Rounding can reach 1 after the shift
+        if x < 1.0 {
+            x
+        } else {
+            0.0
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radical_inverse_digits() {
+        assert_eq!(radical_inverse(2, 0), 0.0);
+        assert_eq!(radical_inverse(2, 1), 0.5);
+        assert_eq!(radical_inverse(2, 6), 0.375);
+        assert!((radical_inverse(3, 5) - 7.0 / 9.0).abs() < 1e-12);
+        assert_eq!(HaltonSampler::new(0).primes[..5], [2, 3, 5, 7, 11]);
+    }
+}
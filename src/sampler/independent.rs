@@ -0,0 +1,39 @@
+use super::{hash, Sampler};
+use crate::rng::Rng;
+
+/// Numbers each sample may draw before running into the next one's
+const SAMPLE_STRIDE: u64 = 1 << 16;
+
+/// Uniform random numbers for every dimension. Each pixel draws from a stream
+/// of its own, each sample from a stretch of it, so the image doesn't depend on
+/// how rows are spread over threads.
+#[derive(Debug, Clone)]
+pub struct IndependentSampler {
+    seed: u64,
+    rng: Rng,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: Rng::new(seed),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize, _count: usize) {
+        let stream = hash(&[pixel.0 as u64, pixel.1 as u64, self.seed]);
+        self.rng = Rng::with_stream(self.seed, stream);
+        self.rng.advance(index as u64 * SAMPLE_STRIDE);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.rng.gen()
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
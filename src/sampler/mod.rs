@@ -0,0 +1,151 @@
+//! Sources of the random numbers a path consumes. Each pixel sample is a point
+//! in a high dimensional unit cube, drawn one or two dimensions at a time: the
+//! position within the pixel first, then the lens, the wavelength and whatever
+//! lights and materials ask for along the path. Samplers that spread the
+//! samples of a pixel evenly over that cube converge faster than independent
+//! random numbers at the same sample count.
+
+mod blue_noise;
+mod halton;
+mod independent;
+mod sobol;
+mod stratified;
+
+use std::fmt::Debug;
+
+use crate::rng::{hash, mix};
+
+pub use blue_noise::{BlueNoiseSampler, BlueNoiseTile};
+pub use halton::HaltonSampler;
+pub use independent::IndependentSampler;
+pub use sobol::SobolSampler;
+pub use stratified::StratifiedSampler;
+
+pub trait Sampler: Send + Sync + Debug {
+    /// Begins sample `index` of the `count` taken for `pixel`, restarting at the first dimension
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize, count: usize);
+
+    /// Next dimension of the current sample, in [0, 1)
+    fn get_1d(&mut self) -> f64;
+
+    /// Next two dimensions of the current sample, spread evenly together where the sampler can
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+
+    /// Position within the pixel, the first two dimensions of a sample
+    fn get_pixel_2d(&mut self) -> (f64, f64) {
+        self.get_2d()
+    }
+
+    /// An independent copy, for another thread
+    fn clone_box(&self) -> Box<dyn Sampler>;
+}
+
+/// 32 random bits as a number in [0, 1)
+fn to_unit(bits: u32) -> f64 {
+    bits as f64 / 4_294_967_296.0
+}
+
+/// Element `i` of a random permutation of `0..n` picked by `seed`, after Kensler
+/// "Correlated Multi-Jittered Sampling"
+fn permutation_element(mut i: u32, n: u32, seed: u32) -> u32 {
+    let mut w = n - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            return (i.wrapping_add(seed)) % n;
+        }
+    }
+}
+
+/// Position of sample `index` among the `count` of a pixel, along with a value
+/// that differs each time `index` wraps past `count`
+fn wrap(index: usize, count: usize) -> (u32, u64) {
+    let count = count.max(1);
+    ((index % count) as u32, (index / count) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// Estimates the integral of a smooth 2D function with each sampler, over many pixels
+    fn error(sampler: &mut dyn Sampler, count: usize) -> f64 {
+        let f = |(x, y): (f64, f64)| (3.0 * x).sin() * (2.0 * y).cos() + x * y;
+        let exact = (1.0 - 3f64.cos()) / 3.0 * 2f64.sin() / 2.0 + 0.25;
+        let pixels = 256;
+        let mut sq_error = 0.0;
+        for p in 0..pixels {
+            let mut sum = 0.0;
+            for i in 0..count {
+                sampler.start_pixel_sample((p, 7), i, count);
+                sampler.get_pixel_2d();
+                sampler.get_1d();
+                sum += f(sampler.get_2d());
+            }
+            sq_error += (sum / count as f64 - exact).powi(2);
+        }
+        (sq_error / pixels as f64).sqrt()
+    }
+
+    #[test]
+    fn convergence() {
+        let independent = error(&mut IndependentSampler::new(1), 16);
+        for sampler in [
+            Box::new(StratifiedSampler::new(1)) as Box<dyn Sampler>,
+            Box::new(HaltonSampler::new(1)),
+            Box::new(SobolSampler::new(1)),
+            Box::new(BlueNoiseSampler::with_tile(
+                Arc::new(BlueNoiseTile::new(16, 1)),
+                1,
+            )),
+        ]
+        .iter_mut()
+        {
+            let e = error(sampler.as_mut(), 16);
+            assert!(
+                e < 0.75 * independent,
+                "{:?}: {} {}",
+                sampler,
+                e,
+                independent
+            );
+        }
+    }
+
+    #[test]
+    fn permutation() {
+        for &n in [1, 5, 16, 100].iter() {
+            let mut seen = (0..n)
+                .map(|i| permutation_element(i, n, 0xdead_beef))
+                .collect::<Vec<_>>();
+            seen.sort_unstable();
+            assert_eq!(seen, (0..n).collect::<Vec<_>>());
+        }
+    }
+}
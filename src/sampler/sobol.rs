@@ -0,0 +1,121 @@
+use super::{hash, mix, permutation_element, to_unit, wrap, Sampler};
+
+/// The first two dimensions of the Sobol sequence, Owen scrambled, and reused for
+/// every dimension or pair of them. Each dimension shuffles the order of the
+/// pixel's samples and scrambles with its own seed, which keeps dimensions from
+/// correlating. Sample counts that are powers of two are spread most evenly.
+#[derive(Debug, Clone)]
+pub struct SobolSampler {
+    seed: u64,
+    pixel: (usize, usize),
+    index: usize,
+    count: usize,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            count: 1,
+            dimension: 0,
+        }
+    }
+
+    /// Shuffled index of the current sample and the seeds to scramble it with
+    fn next(&mut self, dimensions: u64) -> (u32, u64) {
+        let (index, round) = wrap(self.index, self.count);
+        let h = hash(&[
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            self.dimension,
+            round,
+            self.seed,
+        ]);
+        self.dimension += dimensions;
+        (
+            permutation_element(index, self.count as u32, h as u32),
+            mix(h),
+        )
+    }
+}
+
+/// First Sobol dimension, the van der Corput sequence, as 32 bit fractions
+pub(super) fn sobol_0(index: u32) -> u32 {
+    index.reverse_bits()
+}
+
+/// Second Sobol dimension, whose generator matrix is Pascal's triangle mod 2
+pub(super) fn sobol_1(mut index: u32) -> u32 {
+    let (mut v, mut x) = (1u32 << 31, 0);
+    while index != 0 {
+        if index & 1 != 0 {
+            x ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    x
+}
+
+/// Random nested permutation of the binary digits of `v`, after Laine and Karras
+/// "Stratified Sampling for Stochastic Transparency"
+pub(super) fn owen_scramble(v: u32, seed: u32) -> u32 {
+    let mut v = v.reverse_bits();
+    v ^= v.wrapping_mul(0x3d20_adea);
+    v = v.wrapping_add(seed);
+    v = v.wrapping_mul((seed >> 16) | 1);
+    v ^= v.wrapping_mul(0x0552_6c56);
+    v ^= v.wrapping_mul(0x53a2_2864);
+    v.reverse_bits()
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize, count: usize) {
+        self.pixel = pixel;
+        self.index = index;
+        self.count = count.max(1);
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (index, seed) = self.next(1);
+        to_unit(owen_scramble(sobol_0(index), seed as u32))
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (index, seed) = self.next(2);
+        (
+            to_unit(owen_scramble(sobol_0(index), seed as u32)),
+            to_unit(owen_scramble(sobol_1(index), (seed >> 32) as u32)),
+        )
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stratified() {
+        // Any power of two first points of a pair fall one in each elementary interval
+        let n = 16;
+        for &(cols, rows) in [(16, 1), (8, 2), (4, 4), (2, 8), (1, 16)].iter() {
+            let mut cells = vec![0; n];
+            for i in 0..n as u32 {
+                let seed = 0x1234_5678;
+                let x = to_unit(owen_scramble(sobol_0(i), seed));
+                let y = to_unit(owen_scramble(sobol_1(i), seed ^ 0xffff));
+                let cell = (x * cols as f64) as usize + cols * (y * rows as f64) as usize;
+                cells[cell] += 1;
+            }
+            assert!(cells.iter().all(|&c| c == 1), "{}x{}", cols, rows);
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use super::{hash, permutation_element, to_unit, wrap, Sampler};
+
+/// Splits each dimension into one stratum per sample of the pixel and jitters
+/// within it. Pairs of dimensions are split into a grid instead, and the strata
+/// are shuffled differently for every dimension so they don't line up.
+#[derive(Debug, Clone)]
+pub struct StratifiedSampler {
+    seed: u64,
+    pixel: (usize, usize),
+    index: usize,
+    count: usize,
+    dimension: u64,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            count: 1,
+            dimension: 0,
+        }
+    }
+
+    /// Stratum of the current sample in a dimension and the hash to jitter within it with
+    fn stratum(&mut self, n: usize) -> (u32, u64) {
+        let (index, round) = wrap(self.index, self.count);
+        let h = hash(&[
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            self.dimension,
+            round,
+            self.seed,
+        ]);
+        let stratum = permutation_element(index % n as u32, n as u32, h as u32);
+        (stratum, hash(&[h, index as u64]))
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize, count: usize) {
+        self.pixel = pixel;
+        self.index = index;
+        self.count = count.max(1);
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let (stratum, h) = self.stratum(self.count);
+        self.dimension += 1;
+        (stratum as f64 + to_unit(h as u32)) / self.count as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        // The squarest grid with exactly one cell per sample, so every cell is as likely
+        let nx = (1..=(self.count as f64).sqrt() as usize)
+            .rev()
+            .find(|&nx| self.count.is_multiple_of(nx))
+            .unwrap_or(1);
+        let ny = self.count / nx;
+        let (stratum, h) = self.stratum(self.count);
+        self.dimension += 2;
+        let (x, y) = (stratum as usize % nx, stratum as usize / nx);
+        (
+            (x as f64 + to_unit(h as u32)) / nx as f64,
+            (y as f64 + to_unit((h >> 32) as u32)) / ny as f64,
+        )
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
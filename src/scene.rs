@@ -1,8 +1,11 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
+use crate::background::Background;
 use crate::camera::Camera;
 use crate::geometry::Vec3;
 use crate::hittable::{Hittable, HittableList, Sphere};
+use crate::light::{Light, LightGroup, LightLink};
 use crate::material::*;
 use crate::perlin::Perlin;
 use crate::rng::Rng;
@@ -12,11 +15,41 @@ pub trait SceneTrait {
     fn scene(&self, width: usize, rng: &mut Rng) -> (usize, Scene);
 }
 
-#[derive(Default)]
 pub struct Scene {
     pub world: HittableList,
     pub camera: Camera,
-    pub background: Vec3,
+    pub background: Arc<dyn Background>,
+    /// Seen by camera rays instead of `background`, which still lights the scene
+    pub camera_background: Option<Arc<dyn Background>>,
+    /// Lights sampled explicitly, on top of emissive geometry
+    pub lights: Vec<Arc<dyn Light>>,
+    /// Sets of lights that also get an image of their own
+    pub light_groups: Vec<LightGroup>,
+    /// Limits the objects a light reaches, keyed by the light's index
+    pub light_links: HashMap<usize, LightLink>,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self {
+            world: HittableList::default(),
+            camera: Camera::default(),
+            background: Arc::new(Vec3::zero()),
+            camera_background: None,
+            lights: Vec::new(),
+            light_groups: Vec::new(),
+            light_links: HashMap::new(),
+        }
+    }
+}
+
+impl Scene {
+    /// Whether the light at index `light` shines on the object with id `object`
+    pub fn light_reaches(&self, light: usize, object: Option<usize>) -> bool {
+        self.light_links
+            .get(&light)
+            .is_none_or(|link| link.reaches(object))
+    }
 }
 
 pub struct RandomScene {}
@@ -97,9 +130,12 @@ impl SceneTrait for RandomScene {
                     } else if choose_mat < 0.95 {
                         let albedo = Vec3::random_range(rng, 0.5, 1.0);
                         let fuzz = rng.range(0.0, 0.5);
-                        Arc::new(Metal { albedo, fuzz })
+                        Arc::new(Metal {
+                            albedo,
+                            fuzz: Arc::new(fuzz),
+                        })
                     } else {
-                        Arc::new(Dielectric { ir: 1.5 })
+                        Arc::new(Dielectric { ir: Arc::new(1.5) })
                     };
 
                     world.push(Sphere {
@@ -111,7 +147,7 @@ impl SceneTrait for RandomScene {
             }
         }
 
-        let mat_1 = Arc::new(Dielectric { ir: 1.5 });
+        let mat_1 = Arc::new(Dielectric { ir: Arc::new(1.5) });
         world.push(Sphere {
             c: Vec3 {
                 x: 0.0,
@@ -143,7 +179,7 @@ impl SceneTrait for RandomScene {
                 y: 0.6,
                 z: 0.5,
             },
-            fuzz: 0.0,
+            fuzz: Arc::new(0.0),
         });
         world.push(Sphere {
             c: Vec3 {
@@ -162,7 +198,8 @@ impl SceneTrait for RandomScene {
             Scene {
                 world,
                 camera,
-                background,
+                background: Arc::new(background),
+                ..Scene::default()
             },
         )
     }
@@ -236,7 +273,8 @@ impl SceneTrait for SimpleScene {
             Scene {
                 world,
                 camera,
-                background,
+                background: Arc::new(background),
+                ..Scene::default()
             },
         )
     }
@@ -307,7 +345,8 @@ impl SceneTrait for Sphereflake {
             Scene {
                 world,
                 camera,
-                background,
+                background: Arc::new(background),
+                ..Scene::default()
             },
         )
     }
@@ -323,7 +362,7 @@ impl Sphereflake {
                 y: 0.7,
                 z: 0.7,
             },
-            fuzz: 0.01,
+            fuzz: Arc::new(0.01),
         });
         let sphere = Box::new(Sphere {
             c: pos,
@@ -448,7 +487,8 @@ impl SceneTrait for PerlinSpheres {
             Scene {
                 world,
                 camera,
-                background,
+                background: Arc::new(background),
+                ..Scene::default()
             },
         )
     }
@@ -525,7 +565,8 @@ impl SceneTrait for CheckeredSpheres {
             Scene {
                 world,
                 camera,
-                background,
+                background: Arc::new(background),
+                ..Scene::default()
             },
         )
     }
@@ -572,6 +613,8 @@ impl SceneTrait for LightSpheres {
             emit: Arc::new(SolidColor {
                 color_value: Vec3::splat(2.0),
             }),
+            profile: None,
+            two_sided: true,
         });
 
         world.push(Sphere {
@@ -600,7 +643,8 @@ impl SceneTrait for LightSpheres {
             Scene {
                 world,
                 camera,
-                background,
+                background: Arc::new(background),
+                ..Scene::default()
             },
         )
     }
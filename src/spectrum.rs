@@ -0,0 +1,256 @@
+use crate::geometry::Vec3;
+use crate::sampler::Sampler;
+
+/// Shortest wavelength (nm) sampled in spectral mode
+pub const LAMBDA_MIN: f64 = 380.0;
+/// Longest wavelength (nm) sampled in spectral mode
+pub const LAMBDA_MAX: f64 = 780.0;
+
+/// Integral of the CIE y matching function over [LAMBDA_MIN, LAMBDA_MAX]
+const CIE_Y_INTEGRAL: f64 = 106.919_734_638;
+/// Luminance of the tabulated D65 spectrum, used to normalize it to Y = 1
+const D65_Y: f64 = 98.852_048_197;
+
+/// CIE D65 relative spectral power, 380nm to 780nm in 10nm steps
+const D65: [f64; 41] = [
+    49.9755, 54.6482, 82.7549, 91.486, 93.4318, 86.6823, 104.865, 117.008, 117.812, 114.861,
+    115.923, 108.811, 109.354, 107.802, 104.790, 107.689, 104.405, 104.046, 100.000, 96.3342,
+    95.788, 88.6856, 90.0062, 89.5991, 87.6987, 83.2886, 83.6992, 80.0268, 80.2146, 82.2778,
+    78.2842, 69.7213, 71.6091, 74.349, 61.604, 69.8856, 75.087, 63.5927, 46.4182, 66.8054, 63.3828,
+];
+
+// Smits' basis spectra, 10 samples spread evenly over 380nm to 720nm
+const SMITS_MIN: f64 = 380.0;
+const SMITS_MAX: f64 = 720.0;
+const SMITS_WHITE: [f64; 10] = [
+    1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+];
+const SMITS_CYAN: [f64; 10] = [
+    0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [f64; 10] = [
+    1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [f64; 10] = [
+    0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [f64; 10] = [
+    0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [f64; 10] = [
+    0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const SMITS_BLUE: [f64; 10] = [
+    1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+/// Picks a wavelength uniformly from the visible range
+#[inline(always)]
+pub fn sample_wavelength(sampler: &mut dyn Sampler) -> f64 {
+    LAMBDA_MIN + (LAMBDA_MAX - LAMBDA_MIN) * sampler.get_1d()
+}
+
+/// CIE 1931 2° matching functions using the multi-lobe fit of Wyman et al. 2013
+pub fn xyz_match(lambda: f64) -> Vec3 {
+    fn g(x: f64, mu: f64, s1: f64, s2: f64) -> f64 {
+        let t = (x - mu) / if x < mu { s1 } else { s2 };
+        (-0.5 * t * t).exp()
+    }
+
+    Vec3 {
+        x: 1.056 * g(lambda, 599.8, 37.9, 31.0) + 0.362 * g(lambda, 442.0, 16.0, 26.7)
+            - 0.065 * g(lambda, 501.1, 20.4, 26.2),
+        y: 0.821 * g(lambda, 568.8, 46.9, 40.5) + 0.286 * g(lambda, 530.9, 16.3, 31.1),
+        z: 1.217 * g(lambda, 437.0, 11.8, 36.0) + 0.681 * g(lambda, 459.0, 26.0, 13.8),
+    }
+}
+
+/// XYZ contribution of a single radiance sample taken at a uniformly sampled wavelength
+pub fn to_xyz(radiance: f64, lambda: f64) -> Vec3 {
+    xyz_match(lambda) * radiance * (LAMBDA_MAX - LAMBDA_MIN) / CIE_Y_INTEGRAL
+}
+
+/// CIE XYZ to linear sRGB (D65 white point)
+pub fn xyz_to_rgb(c: Vec3) -> Vec3 {
+    Vec3 {
+        x: 3.2404542 * c.x - 1.5371385 * c.y - 0.4985314 * c.z,
+        y: -0.9692660 * c.x + 1.8760108 * c.y + 0.0415560 * c.z,
+        z: 0.0556434 * c.x - 0.2040259 * c.y + 1.0572252 * c.z,
+    }
+}
+
+/// Upsamples a linear RGB reflectance to its spectral value at `lambda`.
+///
+/// Uses Smits' method: the color is split into a white part plus at most one
+/// secondary (cyan, magenta, yellow) and one primary (red, green, blue) part.
+pub fn rgb_to_reflectance(c: Vec3, lambda: f64) -> f64 {
+    let r = c.x.max(0.0);
+    let g = c.y.max(0.0);
+    let b = c.z.max(0.0);
+    let s = |table: &[f64; 10]| smits_lookup(table, lambda);
+
+    if r <= g && r <= b {
+        r * s(&SMITS_WHITE)
+            + if g <= b {
+                (g - r) * s(&SMITS_CYAN) + (b - g) * s(&SMITS_BLUE)
+            } else {
+                (b - r) * s(&SMITS_CYAN) + (g - b) * s(&SMITS_GREEN)
+            }
+    } else if g <= r && g <= b {
+        g * s(&SMITS_WHITE)
+            + if r <= b {
+                (r - g) * s(&SMITS_MAGENTA) + (b - r) * s(&SMITS_BLUE)
+            } else {
+                (b - g) * s(&SMITS_MAGENTA) + (r - b) * s(&SMITS_RED)
+            }
+    } else {
+        b * s(&SMITS_WHITE)
+            + if r <= g {
+                (r - b) * s(&SMITS_YELLOW) + (g - r) * s(&SMITS_GREEN)
+            } else {
+                (g - b) * s(&SMITS_YELLOW) + (r - g) * s(&SMITS_RED)
+            }
+    }
+}
+
+/// Upsamples a linear RGB emission to its spectral value at `lambda`.
+/// The reflectance spectrum is lit by D65 so that RGB white stays white.
+pub fn rgb_to_illuminant(c: Vec3, lambda: f64) -> f64 {
+    rgb_to_reflectance(c, lambda) * d65(lambda) / D65_Y
+}
+
+/// Path throughput helper. In RGB mode `c` is passed through, in spectral
+/// mode every channel holds the upsampled reflectance at `lambda`.
+#[inline(always)]
+pub fn reflectance(c: Vec3, lambda: Option<f64>) -> Vec3 {
+    match lambda {
+        Some(l) => Vec3::splat(rgb_to_reflectance(c, l)),
+        None => c,
+    }
+}
+
+/// Same as `reflectance` but for emitted radiance.
+#[inline(always)]
+pub fn illuminant(c: Vec3, lambda: Option<f64>) -> Vec3 {
+    match lambda {
+        Some(l) => Vec3::splat(rgb_to_illuminant(c, l)),
+        None => c,
+    }
+}
+
+/// Spectral radiance of a blackbody at `kelvin` by Planck's law, `lambda` in nm
+pub fn blackbody(lambda: f64, kelvin: f64) -> f64 {
+    const H: f64 = 6.626_070_15e-34;
+    const C: f64 = 2.997_924_58e8;
+    const KB: f64 = 1.380_649e-23;
+
+    let l = lambda * 1e-9;
+    2.0 * H * C * C / (l.powi(5) * ((H * C / (l * KB * kelvin)).exp() - 1.0))
+}
+
+/// Linear RGB color of a blackbody at `kelvin`, scaled to a luminance of 1.
+/// Channels outside of the sRGB gamut are clamped.
+pub fn blackbody_rgb(kelvin: f64) -> Vec3 {
+    let mut xyz = Vec3::zero();
+    let mut lambda = LAMBDA_MIN;
+    while lambda <= LAMBDA_MAX {
+        xyz += xyz_match(lambda) * blackbody(lambda, kelvin);
+        lambda += 1.0;
+    }
+
+    let rgb = xyz_to_rgb(xyz);
+    let rgb = Vec3 {
+        x: rgb.x.max(0.0),
+        y: rgb.y.max(0.0),
+        z: rgb.z.max(0.0),
+    };
+    rgb / rgb.luminance()
+}
+
+fn d65(lambda: f64) -> f64 {
+    lerp_table(&D65, LAMBDA_MIN, LAMBDA_MAX, lambda)
+}
+
+fn smits_lookup(table: &[f64; 10], lambda: f64) -> f64 {
+    lerp_table(table, SMITS_MIN, SMITS_MAX, lambda)
+}
+
+/// Linearly interpolates evenly spaced samples, clamping outside of [min, max]
+fn lerp_table(table: &[f64], min: f64, max: f64, lambda: f64) -> f64 {
+    let n = table.len() - 1;
+    let t = ((lambda - min) / (max - min)).clamp(0.0, 1.0) * n as f64;
+    let i = (t as usize).min(n - 1);
+    let f = t - i as f64;
+    table[i] * (1.0 - f) + table[i + 1] * f
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn integrate_rgb<F: Fn(f64) -> f64>(spectrum: F) -> Vec3 {
+        const N: usize = 4000;
+        let mut xyz = Vec3::zero();
+        for i in 0..N {
+            let lambda = LAMBDA_MIN + (LAMBDA_MAX - LAMBDA_MIN) * (i as f64 + 0.5) / N as f64;
+            xyz += to_xyz(spectrum(lambda), lambda);
+        }
+        xyz_to_rgb(xyz / N as f64)
+    }
+
+    #[test]
+    fn white_illuminant() {
+        let rgb = integrate_rgb(|l| rgb_to_illuminant(Vec3::splat(1.0), l));
+        assert!((rgb - Vec3::splat(1.0)).len() < 0.01);
+    }
+
+    #[test]
+    fn blackbody_color() {
+        let warm = blackbody_rgb(2700.0);
+        let daylight = blackbody_rgb(6500.0);
+        let cool = blackbody_rgb(10000.0);
+        assert!(warm.x > warm.z && cool.z > cool.x);
+        assert!((daylight - Vec3::splat(1.0)).len() < 0.15);
+        for c in [warm, daylight, cool].iter() {
+            assert!((c.luminance() - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn reflectance_bounds() {
+        let white = Vec3::splat(1.0);
+        let black = Vec3::zero();
+        for i in 0..=40 {
+            let l = LAMBDA_MIN + 10.0 * i as f64;
+            assert!((rgb_to_reflectance(white, l) - 1.0).abs() < 1e-3);
+            assert_eq!(rgb_to_reflectance(black, l), 0.0);
+        }
+    }
+
+    #[test]
+    fn primaries() {
+        let red = integrate_rgb(|l| {
+            rgb_to_illuminant(
+                Vec3 {
+                    x: 1.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                l,
+            )
+        });
+        assert!(red.x > red.y && red.x > red.z);
+
+        let blue = integrate_rgb(|l| {
+            rgb_to_illuminant(
+                Vec3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 1.0,
+                },
+                l,
+            )
+        });
+        assert!(blue.z > blue.x && blue.z > blue.y);
+    }
+}
@@ -0,0 +1,785 @@
+//! Texture graphs described as text, so looks can be built without writing Rust.
+//!
+//! A graph is a list of s-expressions. Any number of `(def name node)` forms
+//! come first, then exactly one node which is the resulting texture. Defined
+//! names can be used as nodes in everything after them. `;` starts a comment.
+//!
+//! ```text
+//! ; weathered stone
+//! (def cracks (worley :seed 3 :scale 6 :feature f2-f1))
+//! (def base (ramp (fbm :seed 1 :scale 2) 0.3 (0.25 0.22 0.2) 0.7 (0.6 0.58 0.55)))
+//! (lerp (mul base 0.4) base (remap cracks 0.0 0.08 0 1))
+//! ```
+//!
+//! Required arguments are positional, optional ones are `:keyword value`
+//! pairs. Wherever a texture is expected a number gives a constant gray and a
+//! list of three numbers a constant color; vectors are written the same way.
+//!
+//! | Node | Arguments |
+//! |------|-----------|
+//! | `color` | `r g b` or `v` |
+//! | `checker` | `odd even` |
+//! | `uv-checker` | `odd even`, `:tiles-u 8 :tiles-v 8` |
+//! | `noise` | `:seed 1234 :scale 1` |
+//! | `fbm` | `:seed :scale 1 :octaves 6 :lacunarity 2 :gain 0.5` |
+//! | `ridged` | as `fbm`, `:offset 1` |
+//! | `marble` | `:seed :scale 4 :turbulence 10 :octaves 7` |
+//! | `wood` | `:seed :scale 8 :turbulence 1 :octaves 4` |
+//! | `worley` | `:seed :scale 1 :feature f1 \| f2 \| f2-f1` |
+//! | `linear-gradient` | `from to`, `:start (0 0 0) :end (1 0 0) :space uv \| object` |
+//! | `radial-gradient` | `inner outer`, `:center (0 0 0) :radius 1 :space uv \| object` |
+//! | `image` | `"path"`, `:color srgb \| linear :wrap repeat \| clamp \| mirror :filter nearest \| bilinear :mipmap true \| false` |
+//! | `transform` | `texture`, `:space uv \| object :scale (1 1 1) :rotation 0 :offset (0 0 0)`, rotation in degrees |
+//! | `mul`, `add` | two or more textures |
+//! | `lerp` | `a b mask` |
+//! | `remap` | `texture from-min from-max to-min to-max` |
+//! | `ramp` | `input` then pairs of `position color` |
+//! | `channel` | `texture r \| g \| b` |
+//!
+//! `:octaves` is at most 16.
+//!
+//! Relative image paths are resolved against the directory of the graph file.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::{
+    Add, Channel, ChannelTexture, CheckerTexture, ColorRamp, Fbm, Filter, ImageTexture, Lerp,
+    LinearGradient, Marble, MipMap, Multiply, NoiseTexture, RadialGradient, Remap,
+    RidgedMultifractal, SolidColor, Texture, TextureSpace, TextureTransform, UvChecker, Wood,
+    Worley, WorleyFeature, WrapMode,
+};
+use crate::geometry::Vec3;
+use crate::perlin::Perlin;
+
+#[derive(Debug)]
+pub enum GraphError {
+    /// Malformed text or an invalid node, at the line and column it starts
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// An image the graph uses could not be loaded
+    Image {
+        path: PathBuf,
+        error: png::DecodingError,
+    },
+    /// The graph file could not be read
+    Io(std::io::Error),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::Syntax {
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}: {}", line, column, message),
+            GraphError::Image { path, error } => {
+                write!(f, "failed to load {}: {}", path.display(), error)
+            }
+            GraphError::Io(error) => write!(f, "failed to read texture graph: {}", error),
+        }
+    }
+}
+
+impl Error for GraphError {}
+
+/// Builds the texture described by `src`, images are looked up relative to the working directory.
+pub fn parse_graph(src: &str) -> Result<Arc<dyn Texture>, GraphError> {
+    build(src, Path::new(""))
+}
+
+/// Reads and builds a texture graph file
+pub fn load_graph(path: &Path) -> Result<Arc<dyn Texture>, GraphError> {
+    let src = fs::read_to_string(path).map_err(GraphError::Io)?;
+    build(&src, path.parent().unwrap_or_else(|| Path::new("")))
+}
+
+fn build(src: &str, base: &Path) -> Result<Arc<dyn Texture>, GraphError> {
+    let exprs = Parser { src, pos: 0 }.parse_all()?;
+    let mut builder = Builder {
+        src,
+        base,
+        defs: HashMap::new(),
+    };
+
+    let (last, defs) = match exprs.split_last() {
+        Some(split) => split,
+        None => return Err(syntax_error(src, src.len(), "empty texture graph")),
+    };
+    for def in defs {
+        builder.define(def)?;
+    }
+    if let Kind::List(items) = &last.kind {
+        if let Some(Kind::Sym(head)) = items.first().map(|e| &e.kind) {
+            if head == "def" {
+                return Err(builder.error(last, "expected a texture after the definitions"));
+            }
+        }
+    }
+    builder.texture(last)
+}
+
+#[derive(Debug)]
+struct Expr {
+    kind: Kind,
+    /// Byte offset into the source, for error messages
+    pos: usize,
+}
+
+#[derive(Debug)]
+enum Kind {
+    Num(f64),
+    Str(String),
+    Sym(String),
+    List(Vec<Expr>),
+}
+
+fn syntax_error(src: &str, pos: usize, message: &str) -> GraphError {
+    let before = &src[..pos];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    GraphError::Syntax {
+        line,
+        column,
+        message: message.to_string(),
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn parse_all(&mut self) -> Result<Vec<Expr>, GraphError> {
+        let mut exprs = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.pos == self.src.len() {
+                return Ok(exprs);
+            }
+            exprs.push(self.parse_expr()?);
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn skip_blank(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == ';' {
+                self.pos = self.src[self.pos..]
+                    .find('\n')
+                    .map_or(self.src.len(), |i| self.pos + i);
+            } else if c.is_whitespace() {
+                self.pos += c.len_utf8();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, GraphError> {
+        let start = self.pos;
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_blank();
+                    match self.peek() {
+                        Some(')') => {
+                            self.pos += 1;
+                            return Ok(Expr {
+                                kind: Kind::List(items),
+                                pos: start,
+                            });
+                        }
+                        Some(_) => items.push(self.parse_expr()?),
+                        None => return Err(syntax_error(self.src, start, "unclosed '('")),
+                    }
+                }
+            }
+            Some(')') => Err(syntax_error(self.src, start, "unexpected ')'")),
+            Some('"') => {
+                let len = self.src[start + 1..]
+                    .find('"')
+                    .ok_or_else(|| syntax_error(self.src, start, "unterminated string"))?;
+                self.pos = start + len + 2;
+                Ok(Expr {
+                    kind: Kind::Str(self.src[start + 1..start + 1 + len].to_string()),
+                    pos: start,
+                })
+            }
+            _ => {
+                let len = self.src[start..]
+                    .find(|c: char| c.is_whitespace() || "();\"".contains(c))
+                    .unwrap_or(self.src.len() - start);
+                self.pos = start + len;
+                let atom = &self.src[start..self.pos];
+                let kind = match atom.parse::<f64>() {
+                    Ok(x) => Kind::Num(x),
+                    Err(_) => Kind::Sym(atom.to_string()),
+                };
+                Ok(Expr { kind, pos: start })
+            }
+        }
+    }
+}
+
+struct Builder<'a> {
+    src: &'a str,
+    base: &'a Path,
+    defs: HashMap<String, Arc<dyn Texture>>,
+}
+
+/// The arguments of a node split into positional and keyword ones
+struct Args<'a> {
+    node: &'a Expr,
+    positional: Vec<&'a Expr>,
+    keywords: Vec<(&'a str, &'a Expr)>,
+}
+
+impl Builder<'_> {
+    fn error(&self, e: &Expr, message: &str) -> GraphError {
+        syntax_error(self.src, e.pos, message)
+    }
+
+    fn define(&mut self, e: &Expr) -> Result<(), GraphError> {
+        match &e.kind {
+            Kind::List(items) if matches!(items.first(), Some(Expr { kind: Kind::Sym(s), .. }) if s == "def") => {
+                match items.as_slice() {
+                    [_, Expr {
+                        kind: Kind::Sym(name),
+                        ..
+                    }, value] => {
+                        let texture = self.texture(value)?;
+                        self.defs.insert(name.clone(), texture);
+                        Ok(())
+                    }
+                    _ => Err(self.error(e, "expected (def name texture)")),
+                }
+            }
+            _ => Err(self.error(e, "only (def ...) may come before the final texture")),
+        }
+    }
+
+    fn number(&self, e: &Expr) -> Result<f64, GraphError> {
+        match e.kind {
+            Kind::Num(x) => Ok(x),
+            _ => Err(self.error(e, "expected a number")),
+        }
+    }
+
+    fn symbol<'e>(&self, e: &'e Expr) -> Result<&'e str, GraphError> {
+        match &e.kind {
+            Kind::Sym(s) => Ok(s),
+            _ => Err(self.error(e, "expected a name")),
+        }
+    }
+
+    /// A number for all three components or a list of three numbers
+    fn vector(&self, e: &Expr) -> Result<Vec3, GraphError> {
+        match &e.kind {
+            Kind::Num(x) => Ok(Vec3::splat(*x)),
+            Kind::List(items) if items.len() == 3 => Ok(Vec3 {
+                x: self.number(&items[0])?,
+                y: self.number(&items[1])?,
+                z: self.number(&items[2])?,
+            }),
+            _ => Err(self.error(e, "expected a number or a list of three numbers")),
+        }
+    }
+
+    fn space(&self, e: &Expr) -> Result<TextureSpace, GraphError> {
+        match self.symbol(e)? {
+            "uv" => Ok(TextureSpace::Uv),
+            "object" => Ok(TextureSpace::Object),
+            _ => Err(self.error(e, "expected uv or object")),
+        }
+    }
+
+    fn texture(&self, e: &Expr) -> Result<Arc<dyn Texture>, GraphError> {
+        let items = match &e.kind {
+            Kind::Num(_) => {
+                return Ok(Arc::new(SolidColor {
+                    color_value: self.vector(e)?,
+                }))
+            }
+            Kind::Sym(name) => {
+                return self
+                    .defs
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| self.error(e, &format!("unknown texture '{}'", name)))
+            }
+            Kind::Str(_) => return Err(self.error(e, "expected a texture")),
+            Kind::List(items) => items,
+        };
+
+        let name = match items.first() {
+            Some(Expr {
+                kind: Kind::Sym(name),
+                ..
+            }) => name.as_str(),
+            Some(Expr {
+                kind: Kind::Num(_), ..
+            }) => {
+                return Ok(Arc::new(SolidColor {
+                    color_value: self.vector(e)?,
+                }))
+            }
+            _ => return Err(self.error(e, "expected a node name")),
+        };
+        let args = self.args(e, &items[1..])?;
+
+        let texture: Arc<dyn Texture> = match name {
+            "color" => {
+                args.check(self, 1..=3, &[])?;
+                let color_value = match args.positional.as_slice() {
+                    [v] => Vec3::splat(self.number(v)?),
+                    [r, g, b] => Vec3 {
+                        x: self.number(r)?,
+                        y: self.number(g)?,
+                        z: self.number(b)?,
+                    },
+                    _ => return Err(self.error(e, "color takes one or three numbers")),
+                };
+                Arc::new(SolidColor { color_value })
+            }
+            "checker" => {
+                args.check(self, 2..=2, &[])?;
+                Arc::new(CheckerTexture {
+                    odd: Box::new(self.texture(args.positional[0])?),
+                    even: Box::new(self.texture(args.positional[1])?),
+                })
+            }
+            "uv-checker" => {
+                args.check(self, 2..=2, &["tiles-u", "tiles-v"])?;
+                Arc::new(UvChecker {
+                    odd: Box::new(self.texture(args.positional[0])?),
+                    even: Box::new(self.texture(args.positional[1])?),
+                    tiles_u: args.number(self, "tiles-u", 8.0)?,
+                    tiles_v: args.number(self, "tiles-v", 8.0)?,
+                })
+            }
+            "noise" => {
+                args.check(self, 0..=0, &["seed", "scale"])?;
+                Arc::new(NoiseTexture {
+                    noise: args.perlin(self)?,
+                    scale: args.number(self, "scale", 1.0)?,
+                })
+            }
+            "fbm" => {
+                args.check(
+                    self,
+                    0..=0,
+                    &["seed", "scale", "octaves", "lacunarity", "gain"],
+                )?;
+                Arc::new(Fbm {
+                    noise: args.perlin(self)?,
+                    scale: args.number(self, "scale", 1.0)?,
+                    octaves: args.octaves(self, 6)?,
+                    lacunarity: args.number(self, "lacunarity", 2.0)?,
+                    gain: args.number(self, "gain", 0.5)?,
+                })
+            }
+            "ridged" => {
+                args.check(
+                    self,
+                    0..=0,
+                    &["seed", "scale", "octaves", "lacunarity", "gain", "offset"],
+                )?;
+                Arc::new(RidgedMultifractal {
+                    noise: args.perlin(self)?,
+                    scale: args.number(self, "scale", 1.0)?,
+                    octaves: args.octaves(self, 6)?,
+                    lacunarity: args.number(self, "lacunarity", 2.0)?,
+                    gain: args.number(self, "gain", 0.5)?,
+                    offset: args.number(self, "offset", 1.0)?,
+                })
+            }
+            "marble" => {
+                args.check(self, 0..=0, &["seed", "scale", "turbulence", "octaves"])?;
+                Arc::new(Marble {
+                    noise: args.perlin(self)?,
+                    scale: args.number(self, "scale", 4.0)?,
+                    turbulence: args.number(self, "turbulence", 10.0)?,
+                    octaves: args.octaves(self, 7)?,
+                })
+            }
+            "wood" => {
+                args.check(self, 0..=0, &["seed", "scale", "turbulence", "octaves"])?;
+                Arc::new(Wood {
+                    noise: args.perlin(self)?,
+                    scale: args.number(self, "scale", 8.0)?,
+                    turbulence: args.number(self, "turbulence", 1.0)?,
+                    octaves: args.octaves(self, 4)?,
+                })
+            }
+            "worley" => {
+                args.check(self, 0..=0, &["seed", "scale", "feature"])?;
+                let feature = match args.get("feature") {
+                    None => WorleyFeature::F1,
+                    Some(f) => match self.symbol(f)? {
+                        "f1" => WorleyFeature::F1,
+                        "f2" => WorleyFeature::F2,
+                        "f2-f1" => WorleyFeature::F2MinusF1,
+                        _ => return Err(self.error(f, "expected f1, f2 or f2-f1")),
+                    },
+                };
+                Arc::new(Worley {
+                    seed: args.count(self, "seed", 0)? as u32,
+                    scale: args.number(self, "scale", 1.0)?,
+                    feature,
+                })
+            }
+            "linear-gradient" => {
+                args.check(self, 2..=2, &["start", "end", "space"])?;
+                Arc::new(LinearGradient {
+                    space: args.space(self)?,
+                    start: args.vector(self, "start", Vec3::zero())?,
+                    end: args.vector(
+                        self,
+                        "end",
+                        Vec3 {
+                            x: 1.0,
+                            y: 0.0,
+                            z: 0.0,
+                        },
+                    )?,
+                    from: self.vector(args.positional[0])?,
+                    to: self.vector(args.positional[1])?,
+                })
+            }
+            "radial-gradient" => {
+                args.check(self, 2..=2, &["center", "radius", "space"])?;
+                Arc::new(RadialGradient {
+                    space: args.space(self)?,
+                    center: args.vector(self, "center", Vec3::zero())?,
+                    radius: args.number(self, "radius", 1.0)?,
+                    inner: self.vector(args.positional[0])?,
+                    outer: self.vector(args.positional[1])?,
+                })
+            }
+            "image" => {
+                args.check(self, 1..=1, &["color", "wrap", "filter", "mipmap"])?;
+                self.image(&args)?
+            }
+            "transform" => {
+                args.check(self, 1..=1, &["space", "scale", "rotation", "offset"])?;
+                let mut transform =
+                    TextureTransform::new(self.texture(args.positional[0])?, args.space(self)?);
+                transform.scale = args.vector(self, "scale", transform.scale)?;
+                transform.rotation = args.number(self, "rotation", 0.0)?.to_radians();
+                transform.offset = args.vector(self, "offset", transform.offset)?;
+                Arc::new(transform)
+            }
+            "mul" | "add" => {
+                args.check(self, 2..=usize::MAX, &[])?;
+                let mut acc = self.texture(args.positional[0])?;
+                for input in &args.positional[1..] {
+                    let b = self.texture(input)?;
+                    acc = if name == "mul" {
+                        Arc::new(Multiply { a: acc, b })
+                    } else {
+                        Arc::new(Add { a: acc, b })
+                    };
+                }
+                acc
+            }
+            "lerp" => {
+                args.check(self, 3..=3, &[])?;
+                Arc::new(Lerp {
+                    a: self.texture(args.positional[0])?,
+                    b: self.texture(args.positional[1])?,
+                    mask: self.texture(args.positional[2])?,
+                })
+            }
+            "remap" => {
+                args.check(self, 5..=5, &[])?;
+                Arc::new(Remap {
+                    texture: self.texture(args.positional[0])?,
+                    from_min: self.number(args.positional[1])?,
+                    from_max: self.number(args.positional[2])?,
+                    to_min: self.number(args.positional[3])?,
+                    to_max: self.number(args.positional[4])?,
+                })
+            }
+            "ramp" => {
+                args.check(self, 3..=usize::MAX, &[])?;
+                let pairs = &args.positional[1..];
+                if pairs.len() % 2 != 0 {
+                    return Err(self.error(e, "ramp stops come in position color pairs"));
+                }
+                let mut stops = Vec::with_capacity(pairs.len() / 2);
+                for pair in pairs.chunks(2) {
+                    let pos = self.number(pair[0])?;
+                    if stops.last().is_some_and(|&(prev, _)| pos < prev) {
+                        return Err(self.error(pair[0], "ramp stops must be in increasing order"));
+                    }
+                    stops.push((pos, self.vector(pair[1])?));
+                }
+                Arc::new(ColorRamp {
+                    input: self.texture(args.positional[0])?,
+                    stops,
+                })
+            }
+            "channel" => {
+                args.check(self, 2..=2, &[])?;
+                let channel = match self.symbol(args.positional[1])? {
+                    "r" => Channel::R,
+                    "g" => Channel::G,
+                    "b" => Channel::B,
+                    _ => return Err(self.error(args.positional[1], "expected r, g or b")),
+                };
+                Arc::new(ChannelTexture {
+                    texture: self.texture(args.positional[0])?,
+                    channel,
+                })
+            }
+            _ => return Err(self.error(e, &format!("unknown node '{}'", name))),
+        };
+
+        Ok(texture)
+    }
+
+    fn image(&self, args: &Args) -> Result<Arc<dyn Texture>, GraphError> {
+        let file = match &args.positional[0].kind {
+            Kind::Str(s) => s,
+            _ => return Err(self.error(args.positional[0], "expected a quoted path")),
+        };
+        let path = self.base.join(file);
+
+        let linear = match args.get("color") {
+            None => false,
+            Some(c) => match self.symbol(c)? {
+                "srgb" => false,
+                "linear" => true,
+                _ => return Err(self.error(c, "expected srgb or linear")),
+            },
+        };
+        let loaded = if linear {
+            ImageTexture::open_linear(&path)
+        } else {
+            ImageTexture::open(&path)
+        };
+        let mut image = loaded.map_err(|error| GraphError::Image { path, error })?;
+
+        if let Some(w) = args.get("wrap") {
+            image.wrap = match self.symbol(w)? {
+                "repeat" => WrapMode::Repeat,
+                "clamp" => WrapMode::Clamp,
+                "mirror" => WrapMode::Mirror,
+                _ => return Err(self.error(w, "expected repeat, clamp or mirror")),
+            };
+        }
+        if let Some(f) = args.get("filter") {
+            image.filter = match self.symbol(f)? {
+                "nearest" => Filter::Nearest,
+                "bilinear" => Filter::Bilinear,
+                _ => return Err(self.error(f, "expected nearest or bilinear")),
+            };
+        }
+
+        let mipmap = match args.get("mipmap") {
+            None => false,
+            Some(m) => match self.symbol(m)? {
+                "true" => true,
+                "false" => false,
+                _ => return Err(self.error(m, "expected true or false")),
+            },
+        };
+        if mipmap {
+            Ok(Arc::new(MipMap::new(image)))
+        } else {
+            Ok(Arc::new(image))
+        }
+    }
+
+    fn args<'e>(&self, node: &'e Expr, items: &'e [Expr]) -> Result<Args<'e>, GraphError> {
+        let mut args = Args {
+            node,
+            positional: Vec::new(),
+            keywords: Vec::new(),
+        };
+        let mut iter = items.iter();
+        while let Some(item) = iter.next() {
+            match &item.kind {
+                Kind::Sym(s) if s.starts_with(':') => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| self.error(item, "keyword without a value"))?;
+                    args.keywords.push((&s[1..], value));
+                }
+                _ => args.positional.push(item),
+            }
+        }
+        Ok(args)
+    }
+}
+
+impl Args<'_> {
+    /// Checks the positional argument count and that only `allowed` keywords are given
+    fn check(
+        &self,
+        b: &Builder,
+        count: std::ops::RangeInclusive<usize>,
+        allowed: &[&str],
+    ) -> Result<(), GraphError> {
+        if !count.contains(&self.positional.len()) {
+            let expected = if count.start() == count.end() {
+                format!("{}", count.start())
+            } else if *count.end() == usize::MAX {
+                format!("at least {}", count.start())
+            } else {
+                format!("{} to {}", count.start(), count.end())
+            };
+            return Err(b.error(
+                self.node,
+                &format!(
+                    "expected {} arguments, got {}",
+                    expected,
+                    self.positional.len()
+                ),
+            ));
+        }
+        for (key, value) in &self.keywords {
+            if !allowed.contains(key) {
+                return Err(b.error(value, &format!("unknown keyword ':{}'", key)));
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Option<&Expr> {
+        self.keywords
+            .iter()
+            .rev()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| *v)
+    }
+
+    fn number(&self, b: &Builder, key: &str, default: f64) -> Result<f64, GraphError> {
+        self.get(key).map_or(Ok(default), |e| b.number(e))
+    }
+
+    fn count(&self, b: &Builder, key: &str, default: usize) -> Result<usize, GraphError> {
+        match self.get(key) {
+            None => Ok(default),
+            Some(e) => {
+                let x = b.number(e)?;
+                if x >= 0.0 && x.fract() == 0.0 {
+                    Ok(x as usize)
+                } else {
+                    Err(b.error(e, "expected a whole number"))
+                }
+            }
+        }
+    }
+
+    /// Past 16 octaves the layers are finer than any footprint and only cost time
+    fn octaves(&self, b: &Builder, default: usize) -> Result<usize, GraphError> {
+        let n = self.count(b, "octaves", default)?;
+        match self.get("octaves") {
+            Some(e) if n > 16 => Err(b.error(e, "expected at most 16 octaves")),
+            _ => Ok(n),
+        }
+    }
+
+    fn vector(&self, b: &Builder, key: &str, default: Vec3) -> Result<Vec3, GraphError> {
+        self.get(key).map_or(Ok(default), |e| b.vector(e))
+    }
+
+    fn space(&self, b: &Builder) -> Result<TextureSpace, GraphError> {
+        self.get("space")
+            .map_or(Ok(TextureSpace::Uv), |e| b.space(e))
+    }
+
+    fn perlin(&self, b: &Builder) -> Result<Perlin, GraphError> {
+        Ok(Perlin::with_seed(self.count(b, "seed", 1234)? as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_of(err: GraphError) -> usize {
+        match err {
+            GraphError::Syntax { line, .. } => line,
+            e => panic!("unexpected error {}", e),
+        }
+    }
+
+    #[test]
+    fn builds_graph() {
+        let src = "
+            ; half red, half blue
+            (def mask (linear-gradient 0 1 :end (1 0 0)))
+            (lerp (color 1 0 0) (0 0 1) (remap mask 0.4 0.6 0 1))
+        ";
+        let t = parse_graph(src).unwrap();
+        let p = Vec3::zero();
+        assert_eq!(
+            t.value(0.1, 0.5, p),
+            Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0
+            }
+        );
+        assert_eq!(
+            t.value(0.5, 0.5, p),
+            Vec3 {
+                x: 0.5,
+                y: 0.0,
+                z: 0.5
+            }
+        );
+        assert_eq!(
+            t.value(0.9, 0.5, p),
+            Vec3 {
+                x: 0.0,
+                y: 0.0,
+                z: 1.0
+            }
+        );
+
+        let ramp = parse_graph("(ramp (channel (0.2 0.5 0.8) g) 0 0 1 (1 0.5 0))").unwrap();
+        assert_eq!(
+            ramp.value(0.0, 0.0, p),
+            Vec3 {
+                x: 0.5,
+                y: 0.25,
+                z: 0.0
+            }
+        );
+
+        let sum = parse_graph("(add 0.25 (mul 0.5 (fbm :seed 2 :octaves 3) 0))").unwrap();
+        assert_eq!(sum.value(0.0, 0.0, p), Vec3::splat(0.25));
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(line_of(parse_graph("\n(mul 1)").unwrap_err()), 2);
+        assert_eq!(line_of(parse_graph("(fbm :sed 3)").unwrap_err()), 1);
+        assert_eq!(line_of(parse_graph("(fbm :octaves 1e12)").unwrap_err()), 1);
+        assert_eq!(line_of(parse_graph("(lerp a b c)").unwrap_err()), 1);
+        assert_eq!(line_of(parse_graph("(color 1\n\n").unwrap_err()), 1);
+        assert_eq!(line_of(parse_graph("(def x 1)").unwrap_err()), 1);
+        assert_eq!(line_of(parse_graph("\n\n(sparkle)").unwrap_err()), 3);
+
+        let missing = parse_graph("(image \"no/such/file.png\")").unwrap_err();
+        assert!(matches!(missing, GraphError::Image { .. }));
+    }
+}
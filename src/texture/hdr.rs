@@ -0,0 +1,263 @@
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use super::ImageTexture;
+use crate::geometry::Vec3;
+
+// Loaders for high dynamic range images. Both formats store linear radiance
+// so no color conversion is applied.
+
+impl ImageTexture {
+    /// Loads a Radiance RGBE (`.hdr`) image, flat or run length encoded
+    pub fn open_hdr(path: &Path) -> Result<Self> {
+        decode_hdr(&fs::read(path)?)
+    }
+
+    /// Loads a portable float map (`.pfm`), color or grayscale
+    pub fn open_pfm(path: &Path) -> Result<Self> {
+        decode_pfm(&fs::read(path)?)
+    }
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn decode_hdr(data: &[u8]) -> Result<ImageTexture> {
+    // Header lines up to an empty one, then the resolution line
+    let mut pos = 0;
+    let mut next_line = || -> Result<&[u8]> {
+        let len = data[pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| invalid("truncated header"))?;
+        let line = &data[pos..pos + len];
+        pos += len + 1;
+        Ok(line)
+    };
+
+    if !next_line()?.starts_with(b"#?") {
+        return Err(invalid("not a Radiance file"));
+    }
+    loop {
+        let line = next_line()?;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with(b"FORMAT=") && line != b"FORMAT=32-bit_rle_rgbe" {
+            return Err(invalid("only RGBE pixels are supported"));
+        }
+    }
+
+    let resolution = String::from_utf8_lossy(next_line()?).into_owned();
+    let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", h, "+X", w] => (h.parse::<usize>(), w.parse::<usize>()),
+        _ => return Err(invalid("unsupported image orientation")),
+    };
+    let (height, width) = match (height, width) {
+        (Ok(h), Ok(w)) if w > 0 && h > 0 && w.checked_mul(h).is_some() => (h, w),
+        _ => return Err(invalid("bad resolution")),
+    };
+
+    // A run packs at most 127 pixels of one of the four channels into two
+    // bytes, so no scanline holds more than 16 pixels per byte
+    if width * height > (data.len() - pos).saturating_mul(16) {
+        return Err(invalid("truncated pixel data"));
+    }
+
+    let mut bytes = data[pos..].iter().copied();
+    let mut next = || bytes.next().ok_or_else(|| invalid("truncated pixel data"));
+
+    let mut texels = Vec::with_capacity(width * height);
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        let first = [next()?, next()?, next()?, next()?];
+        let rle = (8..0x8000).contains(&width) && first[0] == 2 && first[1] == 2;
+
+        if rle {
+            if (first[2] as usize) << 8 | first[3] as usize != width {
+                return Err(invalid("scanline width mismatch"));
+            }
+            // Each channel is stored separately as runs and literals
+            for channel in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = next()? as usize;
+                    let (run, count) = if count > 128 {
+                        (true, count - 128)
+                    } else {
+                        (false, count)
+                    };
+                    if count == 0 || x + count > width {
+                        return Err(invalid("bad run length"));
+                    }
+                    let value = if run { next()? } else { 0 };
+                    for pixel in &mut scanline[x..x + count] {
+                        pixel[channel] = if run { value } else { next()? };
+                    }
+                    x += count;
+                }
+            }
+        } else {
+            scanline[0] = first;
+            for pixel in scanline.iter_mut().skip(1) {
+                *pixel = [next()?, next()?, next()?, next()?];
+            }
+        }
+
+        texels.extend(scanline.iter().map(|&[r, g, b, e]| {
+            if e == 0 {
+                Vec3::zero()
+            } else {
+                let f = 2f64.powi(e as i32 - 136);
+                Vec3 {
+                    x: (r as f64 + 0.5) * f,
+                    y: (g as f64 + 0.5) * f,
+                    z: (b as f64 + 0.5) * f,
+                }
+            }
+        }));
+    }
+
+    Ok(ImageTexture::new(width, height, texels))
+}
+
+fn decode_pfm(data: &[u8]) -> Result<ImageTexture> {
+    // Three whitespace separated header tokens, then a single whitespace byte
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while tokens.len() < 4 {
+        while pos < data.len() && data[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        let start = pos;
+        while pos < data.len() && !data[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if start == pos {
+            return Err(invalid("truncated header"));
+        }
+        tokens.push(String::from_utf8_lossy(&data[start..pos]).into_owned());
+    }
+    pos += 1;
+
+    let channels = match tokens[0].as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid("not a PFM file")),
+    };
+    let parse = |s: &str| s.parse::<usize>().map_err(|_| invalid("bad resolution"));
+    let width = parse(&tokens[1])?;
+    let height = parse(&tokens[2])?;
+    let scale = tokens[3].parse::<f64>().map_err(|_| invalid("bad scale"))?;
+    let little_endian = scale < 0.0;
+
+    let end = width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(4 * channels))
+        .and_then(|n| n.checked_add(pos))
+        .ok_or_else(|| invalid("bad resolution"))?;
+    let body = data
+        .get(pos..end)
+        .ok_or_else(|| invalid("truncated pixel data"))?;
+    let values = body
+        .chunks(4)
+        .map(|b| {
+            let b = [b[0], b[1], b[2], b[3]];
+            if little_endian {
+                f32::from_le_bytes(b) as f64
+            } else {
+                f32::from_be_bytes(b) as f64
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Rows are stored bottom to top
+    let mut texels = Vec::with_capacity(width * height);
+    for y in (0..height).rev() {
+        for x in 0..width {
+            let i = (y * width + x) * channels;
+            texels.push(if channels == 3 {
+                Vec3 {
+                    x: values[i],
+                    y: values[i + 1],
+                    z: values[i + 2],
+                }
+            } else {
+                Vec3::splat(values[i])
+            });
+        }
+    }
+
+    Ok(ImageTexture::new(width, height, texels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hdr() {
+        let mut flat = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 1\n".to_vec();
+        flat.extend_from_slice(&[128, 64, 0, 129, 0, 0, 0, 0]);
+        let image = decode_hdr(&flat).unwrap();
+        assert_eq!((image.width(), image.height()), (1, 2));
+        assert_eq!(
+            image.texel(0, 0),
+            Vec3 {
+                x: 128.5 / 128.0,
+                y: 64.5 / 128.0,
+                z: 0.5 / 128.0,
+            }
+        );
+        assert_eq!(image.texel(0, 1), Vec3::zero());
+
+        // A width 8 scanline, red as a run, green as literals, blue and exponent as runs
+        let mut rle = b"#?RADIANCE\n\n-Y 1 +X 8\n".to_vec();
+        rle.extend_from_slice(&[2, 2, 0, 8, 136, 64, 8, 0, 1, 2, 3, 4, 5, 6, 7, 136, 0, 136]);
+        rle.push(128);
+        let image = decode_hdr(&rle).unwrap();
+        assert_eq!(image.texel(3, 0).x, 64.5 / 256.0);
+        assert_eq!(image.texel(3, 0).y, 3.5 / 256.0);
+
+        assert!(decode_hdr(b"P6\n").is_err());
+        assert!(decode_hdr(b"#?RADIANCE\n\n-Y 2 +X 1\n\x80\x40").is_err());
+        let huge = b"#?RADIANCE\n\n-Y 2147483648 +X 2147483648\n\x02\x02";
+        assert!(decode_hdr(huge).is_err());
+    }
+
+    #[test]
+    fn pfm() {
+        let mut data = b"PF\n1 2\n-1.0\n".to_vec();
+        for v in [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0].iter() {
+            data.extend_from_slice(&v.to_le_bytes());
+        }
+        let image = decode_pfm(&data).unwrap();
+        // The first stored row is the bottom one
+        assert_eq!(
+            image.texel(0, 1),
+            Vec3 {
+                x: 1.0,
+                y: 2.0,
+                z: 3.0
+            }
+        );
+        assert_eq!(
+            image.texel(0, 0),
+            Vec3 {
+                x: 4.0,
+                y: 5.0,
+                z: 6.0
+            }
+        );
+
+        let mut gray = b"Pf 1 1 1.0\n".to_vec();
+        gray.extend_from_slice(&0.5f32.to_be_bytes());
+        assert_eq!(decode_pfm(&gray).unwrap().texel(0, 0), Vec3::splat(0.5));
+
+        assert!(decode_pfm(b"PF\n1 1\n-1.0\n\0\0").is_err());
+        let huge = format!("PF\n{} 3\n-1.0\n", usize::MAX / 8);
+        assert!(decode_pfm(huge.as_bytes()).is_err());
+    }
+}
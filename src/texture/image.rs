@@ -0,0 +1,276 @@
+use std::fs::File;
+use std::path::Path;
+
+use super::Texture;
+use crate::geometry::Vec3;
+
+/// How texel lookups outside of [0, 1] are resolved
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WrapMode {
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+}
+
+/// A texture backed by an image, sampled by `u`, `v` with `v = 0` at the bottom row.
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    texels: Vec<Vec3>,
+    alpha: Vec<f64>,
+    pub wrap: WrapMode,
+    pub filter: Filter,
+}
+
+impl ImageTexture {
+    /// Builds a texture from linear texels stored row by row, top row first.
+    pub fn new(width: usize, height: usize, texels: Vec<Vec3>) -> Self {
+        assert_eq!(texels.len(), width * height, "Texel count mismatch");
+        Self {
+            width,
+            height,
+            texels,
+            alpha: vec![1.0; width * height],
+            wrap: WrapMode::Repeat,
+            filter: Filter::Bilinear,
+        }
+    }
+
+    /// Loads a color PNG, converting from sRGB to linear.
+    pub fn open(path: &Path) -> Result<Self, png::DecodingError> {
+        Self::load(path, true)
+    }
+
+    /// Loads a PNG holding non-color data (normal maps, heights, masks) as is.
+    pub fn open_linear(path: &Path) -> Result<Self, png::DecodingError> {
+        Self::load(path, false)
+    }
+
+    fn load(path: &Path, srgb: bool) -> Result<Self, png::DecodingError> {
+        // The default transformations expand to 8 bit gray, gray alpha, rgb or rgba
+        let decoder = png::Decoder::new(File::open(path)?);
+        let (info, mut reader) = decoder.read_info()?;
+        let mut buf = vec![0; info.buffer_size()];
+        reader.next_frame(&mut buf)?;
+
+        let channels = match info.color_type {
+            png::ColorType::Grayscale => 1,
+            png::ColorType::GrayscaleAlpha => 2,
+            png::ColorType::RGB => 3,
+            png::ColorType::RGBA => 4,
+            png::ColorType::Indexed => {
+                return Err(png::DecodingError::Format("Unexpanded palette".into()))
+            }
+        };
+        let decode = |b: u8| {
+            let c = b as f64 / 255.0;
+            if srgb {
+                srgb_to_linear(c)
+            } else {
+                c
+            }
+        };
+
+        let width = info.width as usize;
+        let height = info.height as usize;
+        let mut texels = Vec::with_capacity(width * height);
+        let mut alpha = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let row = &buf[y * info.line_size..];
+            for px in row.chunks(channels).take(width) {
+                texels.push(match channels {
+                    1 | 2 => Vec3::splat(decode(px[0])),
+                    _ => Vec3 {
+                        x: decode(px[0]),
+                        y: decode(px[1]),
+                        z: decode(px[2]),
+                    },
+                });
+                alpha.push(match channels {
+                    2 => px[1] as f64 / 255.0,
+                    4 => px[3] as f64 / 255.0,
+                    _ => 1.0,
+                });
+            }
+        }
+
+        Ok(Self {
+            width,
+            height,
+            texels,
+            alpha,
+            wrap: WrapMode::Repeat,
+            filter: Filter::Bilinear,
+        })
+    }
+
+    /// A grayscale texture holding this image's alpha channel, for use with `AlphaMask`.
+    pub fn alpha(&self) -> Self {
+        Self {
+            texels: self.alpha.iter().map(|a| Vec3::splat(*a)).collect(),
+            ..self.clone()
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Texel at integer coordinates, resolved with the wrap mode
+    pub fn texel(&self, x: i64, y: i64) -> Vec3 {
+        let x = wrap(x, self.width, self.wrap);
+        let y = wrap(y, self.height, self.wrap);
+        self.texels[y * self.width + x]
+    }
+
+    /// Samples with the texture's filter, `(u, v)` in the same convention as `Texture::value`.
+    pub fn sample(&self, u: f64, v: f64) -> Vec3 {
+        let x = u * self.width as f64;
+        let y = (1.0 - v) * self.height as f64;
+
+        match self.filter {
+            Filter::Nearest => self.texel(x.floor() as i64, y.floor() as i64),
+            Filter::Bilinear => {
+                let x = x - 0.5;
+                let y = y - 0.5;
+                let x0 = x.floor();
+                let y0 = y.floor();
+                let fx = x - x0;
+                let fy = y - y0;
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                (1.0 - fx) * (1.0 - fy) * self.texel(x0, y0)
+                    + fx * (1.0 - fy) * self.texel(x0 + 1, y0)
+                    + (1.0 - fx) * fy * self.texel(x0, y0 + 1)
+                    + fx * fy * self.texel(x0 + 1, y0 + 1)
+            }
+        }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: Vec3) -> Vec3 {
+        self.sample(u, v)
+    }
+}
+
+fn wrap(i: i64, n: usize, mode: WrapMode) -> usize {
+    let n = n as i64;
+    let i = match mode {
+        WrapMode::Repeat => i.rem_euclid(n),
+        WrapMode::Clamp => i.clamp(0, n - 1),
+        WrapMode::Mirror => {
+            let m = i.rem_euclid(2 * n);
+            if m >= n {
+                2 * n - 1 - m
+            } else {
+                m
+            }
+        }
+    };
+    i as usize
+}
+
+/// sRGB transfer function to linear
+pub fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufWriter;
+
+    fn gradient() -> ImageTexture {
+        // 2x2, top row black/red, bottom row green/blue
+        ImageTexture::new(
+            2,
+            2,
+            vec![
+                Vec3::zero(),
+                Vec3 {
+                    x: 1.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                Vec3 {
+                    x: 0.0,
+                    y: 1.0,
+                    z: 0.0,
+                },
+                Vec3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 1.0,
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn wrap_modes() {
+        assert_eq!(wrap(-1, 4, WrapMode::Repeat), 3);
+        assert_eq!(wrap(5, 4, WrapMode::Repeat), 1);
+        assert_eq!(wrap(-1, 4, WrapMode::Clamp), 0);
+        assert_eq!(wrap(9, 4, WrapMode::Clamp), 3);
+        assert_eq!(wrap(-1, 4, WrapMode::Mirror), 0);
+        assert_eq!(wrap(4, 4, WrapMode::Mirror), 3);
+        assert_eq!(wrap(6, 4, WrapMode::Mirror), 1);
+    }
+
+    #[test]
+    fn nearest() {
+        let mut t = gradient();
+        t.filter = Filter::Nearest;
+        assert_eq!(t.sample(0.75, 0.75).x, 1.0);
+        assert_eq!(t.sample(0.25, 0.25).y, 1.0);
+        assert_eq!(t.sample(1.25, 0.25).y, 1.0);
+    }
+
+    #[test]
+    fn bilinear() {
+        let mut t = gradient();
+        t.wrap = WrapMode::Clamp;
+        let c = t.sample(0.5, 0.5);
+        assert!((c - Vec3::splat(0.25)).len() < 1e-12);
+        assert_eq!(t.sample(0.0, 1.0), Vec3::zero());
+    }
+
+    #[test]
+    fn open_png() {
+        let path = std::env::temp_dir().join("wasm_raytracer_image_texture.png");
+        {
+            let file = File::create(&path).unwrap();
+            let mut encoder = png::Encoder::new(BufWriter::new(file), 2, 1);
+            encoder.set_color(png::ColorType::RGBA);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer
+                .write_image_data(&[255, 0, 0, 255, 0, 188, 0, 0])
+                .unwrap();
+        }
+
+        let mut t = ImageTexture::open(&path).unwrap();
+        t.filter = Filter::Nearest;
+        assert_eq!(t.sample(0.25, 0.5).x, 1.0);
+        assert!((t.sample(0.75, 0.5).y - 0.5).abs() < 0.01);
+        assert_eq!(t.alpha().sample(0.75, 0.5).x, 0.0);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+use super::{Filter, Footprint, ImageTexture, Texture};
+use crate::geometry::Vec3;
+
+/// An image pyramid, each level half the size of the previous one.
+/// Filtered lookups blend the two levels closest to the pixel footprint.
+#[derive(Debug, Clone)]
+pub struct MipMap {
+    levels: Vec<ImageTexture>,
+}
+
+impl MipMap {
+    pub fn new(image: ImageTexture) -> Self {
+        let mut levels = vec![image];
+
+        loop {
+            let prev = levels.last().unwrap();
+            // Empty sides stay empty, so stop at 1x1 or anything smaller
+            if prev.width() <= 1 && prev.height() <= 1 {
+                break;
+            }
+
+            // Odd sizes round up, the last row or column averaging only what is left
+            let width = prev.width().div_ceil(2);
+            let height = prev.height().div_ceil(2);
+            let xs = |x: usize| 2 * x..(2 * x + 2).min(prev.width());
+            let ys = |y: usize| 2 * y..(2 * y + 2).min(prev.height());
+            let mut texels = Vec::with_capacity(width * height);
+            for y in 0..height {
+                for x in 0..width {
+                    let mut sum = Vec3::zero();
+                    let mut count = 0;
+                    for sy in ys(y) {
+                        for sx in xs(x) {
+                            sum += prev.texel(sx as i64, sy as i64);
+                            count += 1;
+                        }
+                    }
+                    texels.push(sum / count as f64);
+                }
+            }
+
+            let mut level = ImageTexture::new(width, height, texels);
+            level.wrap = prev.wrap;
+            level.filter = Filter::Bilinear;
+            levels.push(level);
+        }
+
+        Self { levels }
+    }
+
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Trilinear lookup at a fractional level of detail
+    pub fn lookup(&self, u: f64, v: f64, lod: f64) -> Vec3 {
+        let lod = lod.clamp(0.0, (self.levels.len() - 1) as f64);
+        let lo = lod.floor() as usize;
+        let hi = (lo + 1).min(self.levels.len() - 1);
+        let f = lod - lo as f64;
+
+        (1.0 - f) * self.levels[lo].sample(u, v) + f * self.levels[hi].sample(u, v)
+    }
+}
+
+impl Texture for MipMap {
+    fn value(&self, u: f64, v: f64, _p: Vec3) -> Vec3 {
+        self.levels[0].sample(u, v)
+    }
+
+    fn value_filtered(&self, u: f64, v: f64, _p: Vec3, fp: &Footprint) -> Vec3 {
+        let base = &self.levels[0];
+        let texels = fp.uv_width * base.width().max(base.height()) as f64;
+        if texels <= 1.0 {
+            return base.sample(u, v);
+        }
+
+        self.lookup(u, v, texels.log2())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pyramid() {
+        // 4x2 stripes, black and white columns
+        let texels = (0..8)
+            .map(|i| Vec3::splat((i % 2) as f64))
+            .collect::<Vec<_>>();
+        let mip = MipMap::new(ImageTexture::new(4, 2, texels));
+
+        assert_eq!(mip.levels(), 3);
+        assert_eq!(mip.lookup(0.125, 0.5, 0.0), Vec3::zero());
+        assert_eq!(mip.lookup(0.1, 0.5, 2.0), Vec3::splat(0.5));
+
+        // A footprint four texels wide averages the stripes
+        let fp = Footprint {
+            width: 0.0,
+            uv_width: 1.0,
+        };
+        let c = mip.value_filtered(0.1, 0.5, Vec3::zero(), &fp);
+        assert!((c - Vec3::splat(0.5)).len() < 1e-12);
+
+        // The last column of an odd width is kept instead of dropped
+        let texels = (0..3).map(|i| Vec3::splat(i as f64)).collect::<Vec<_>>();
+        let mip = MipMap::new(ImageTexture::new(3, 1, texels));
+        assert_eq!(mip.levels(), 3);
+        assert_eq!(mip.levels[1].width(), 2);
+        assert_eq!(mip.levels[1].texel(0, 0), Vec3::splat(0.5));
+        assert_eq!(mip.levels[1].texel(1, 0), Vec3::splat(2.0));
+
+        assert_eq!(MipMap::new(ImageTexture::new(0, 0, Vec::new())).levels(), 1);
+        assert_eq!(MipMap::new(ImageTexture::new(0, 4, Vec::new())).levels(), 3);
+    }
+}
@@ -0,0 +1,162 @@
+mod graph;
+mod hdr;
+mod image;
+mod mipmap;
+mod node;
+mod procedural;
+mod scalar;
+mod transform;
+
+use std::f64::consts::PI;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::geometry::Vec3;
+use crate::perlin::Perlin;
+
+pub use graph::{load_graph, parse_graph, GraphError};
+pub use image::{Filter, ImageTexture, WrapMode};
+pub use mipmap::MipMap;
+pub use node::{Add, Channel, ChannelTexture, ColorRamp, Lerp, Multiply, Remap};
+pub use procedural::{
+    Fbm, LinearGradient, Marble, RadialGradient, RidgedMultifractal, Wood, Worley, WorleyFeature,
+};
+pub use scalar::{Luminance, ScalarChannel, ScalarRange, ScalarTexture};
+pub use transform::{TextureSpace, TextureTransform};
+
+pub trait Texture: Send + Sync + Debug {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3;
+
+    /// Value averaged over the area a pixel covers. Defaults to point sampling.
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, _fp: &Footprint) -> Vec3 {
+        self.value(u, v, p)
+    }
+}
+
+impl Texture for Arc<dyn Texture> {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        (**self).value(u, v, p)
+    }
+
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, fp: &Footprint) -> Vec3 {
+        (**self).value_filtered(u, v, p, fp)
+    }
+}
+
+/// Size of the area covered by one pixel around a hit.
+/// Zero when unknown, which makes filtered lookups point sample.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Footprint {
+    /// Width in world space
+    pub width: f64,
+    /// Width in texture space
+    pub uv_width: f64,
+}
+
+#[derive(Debug)]
+pub struct SolidColor {
+    pub color_value: Vec3,
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: Vec3) -> Vec3 {
+        self.color_value
+    }
+}
+
+#[derive(Debug)]
+pub struct CheckerTexture {
+    pub odd: Box<dyn Texture>,
+    pub even: Box<dyn Texture>,
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let sines = (10.0 * p.x).sin() * (10.0 * p.y).sin() * (10.0 * p.z).sin();
+        if sines < 0.0 {
+            self.odd.value(u, v, p)
+        } else {
+            self.even.value(u, v, p)
+        }
+    }
+
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, fp: &Footprint) -> Vec3 {
+        let sines = (10.0 * p.x).sin() * (10.0 * p.y).sin() * (10.0 * p.z).sin();
+        let odd = self.odd.value_filtered(u, v, p, fp);
+        let even = self.even.value_filtered(u, v, p, fp);
+        let point = if sines < 0.0 { odd } else { even };
+
+        // Fade to the average once a pixel covers more than half a square
+        let fade = (10.0 * fp.width / PI - 0.5).clamp(0.0, 1.0);
+        (1.0 - fade) * point + fade * 0.5 * (odd + even)
+    }
+}
+
+/// Checkerboard in texture space with `tiles_u` by `tiles_v` squares over [0, 1]
+#[derive(Debug)]
+pub struct UvChecker {
+    pub odd: Box<dyn Texture>,
+    pub even: Box<dyn Texture>,
+    pub tiles_u: f64,
+    pub tiles_v: f64,
+}
+
+impl Texture for UvChecker {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let tile = (u * self.tiles_u).floor() + (v * self.tiles_v).floor();
+        if tile.rem_euclid(2.0) == 0.0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, fp: &Footprint) -> Vec3 {
+        let ds = fp.uv_width * self.tiles_u;
+        let dt = fp.uv_width * self.tiles_v;
+        if ds <= 0.0 || dt <= 0.0 {
+            return self.value(u, v, p);
+        }
+
+        // Box filter the checker analytically. `bump_int` integrates the
+        // 1D square wave that is 1 over odd tiles.
+        fn bump_int(x: f64) -> f64 {
+            (x / 2.0).floor() + 2.0 * (x / 2.0 - (x / 2.0).floor() - 0.5).max(0.0)
+        }
+        let s = u * self.tiles_u;
+        let t = v * self.tiles_v;
+        let s_int = (bump_int(s + ds) - bump_int(s - ds)) / (2.0 * ds);
+        let t_int = (bump_int(t + dt) - bump_int(t - dt)) / (2.0 * dt);
+        let odd_area = s_int + t_int - 2.0 * s_int * t_int;
+
+        (1.0 - odd_area) * self.even.value_filtered(u, v, p, fp)
+            + odd_area * self.odd.value_filtered(u, v, p, fp)
+    }
+}
+
+#[derive(Debug)]
+pub struct NoiseTexture {
+    pub noise: Perlin,
+    pub scale: f64,
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        Vec3::splat(1.0) * 0.5 * (1.0 + (self.scale * p.z + 10.0 * self.noise.turb(p, 7)).sin())
+    }
+
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, fp: &Footprint) -> Vec3 {
+        let w = fp.width;
+        if w <= 0.0 {
+            return self.value(u, v, p);
+        }
+
+        // Drop the octaves finer than the footprint, each one doubles the frequency
+        let octaves = (-1.0 - w.log2()).clamp(1.0, 7.0) as usize;
+        let stripes = 0.5 * (1.0 + (self.scale * p.z + 10.0 * self.noise.turb(p, octaves)).sin());
+
+        // Fade the stripes to their mean when they get thinner than a pixel
+        let fade = ((self.scale + 10.0) * w / PI - 0.5).clamp(0.0, 1.0);
+        Vec3::splat((1.0 - fade) * stripes + fade * 0.5)
+    }
+}
@@ -0,0 +1,230 @@
+use std::sync::Arc;
+
+use super::{Footprint, Texture};
+use crate::geometry::Vec3;
+
+// Combinators that build textures out of other textures. Scalar inputs such
+// as masks and ramp inputs are read from the x channel.
+
+/// Component-wise product
+#[derive(Debug, Clone)]
+pub struct Multiply {
+    pub a: Arc<dyn Texture>,
+    pub b: Arc<dyn Texture>,
+}
+
+impl Texture for Multiply {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.a.value(u, v, p) * self.b.value(u, v, p)
+    }
+
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, fp: &Footprint) -> Vec3 {
+        self.a.value_filtered(u, v, p, fp) * self.b.value_filtered(u, v, p, fp)
+    }
+}
+
+/// Component-wise sum
+#[derive(Debug, Clone)]
+pub struct Add {
+    pub a: Arc<dyn Texture>,
+    pub b: Arc<dyn Texture>,
+}
+
+impl Texture for Add {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.a.value(u, v, p) + self.b.value(u, v, p)
+    }
+
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, fp: &Footprint) -> Vec3 {
+        self.a.value_filtered(u, v, p, fp) + self.b.value_filtered(u, v, p, fp)
+    }
+}
+
+/// Blends from `a` where `mask` is 0 to `b` where it is 1
+#[derive(Debug, Clone)]
+pub struct Lerp {
+    pub a: Arc<dyn Texture>,
+    pub b: Arc<dyn Texture>,
+    pub mask: Arc<dyn Texture>,
+}
+
+impl Texture for Lerp {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let t = self.mask.value(u, v, p).x;
+        (1.0 - t) * self.a.value(u, v, p) + t * self.b.value(u, v, p)
+    }
+
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, fp: &Footprint) -> Vec3 {
+        let t = self.mask.value_filtered(u, v, p, fp).x;
+        (1.0 - t) * self.a.value_filtered(u, v, p, fp) + t * self.b.value_filtered(u, v, p, fp)
+    }
+}
+
+/// Linearly maps every channel from [from_min, from_max] to [to_min, to_max], clamped.
+/// An empty input range is a step from `to_min` to `to_max` at `from_min`.
+#[derive(Debug, Clone)]
+pub struct Remap {
+    pub texture: Arc<dyn Texture>,
+    pub from_min: f64,
+    pub from_max: f64,
+    pub to_min: f64,
+    pub to_max: f64,
+}
+
+impl Remap {
+    fn remap(&self, x: f64) -> f64 {
+        let t = if self.from_max == self.from_min {
+            if x >= self.from_min {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            ((x - self.from_min) / (self.from_max - self.from_min)).clamp(0.0, 1.0)
+        };
+        self.to_min + t * (self.to_max - self.to_min)
+    }
+
+    fn apply(&self, c: Vec3) -> Vec3 {
+        Vec3 {
+            x: self.remap(c.x),
+            y: self.remap(c.y),
+            z: self.remap(c.z),
+        }
+    }
+}
+
+impl Texture for Remap {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.apply(self.texture.value(u, v, p))
+    }
+
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, fp: &Footprint) -> Vec3 {
+        self.apply(self.texture.value_filtered(u, v, p, fp))
+    }
+}
+
+/// Maps the input to a color by interpolating between stops
+#[derive(Debug, Clone)]
+pub struct ColorRamp {
+    pub input: Arc<dyn Texture>,
+    /// (position, color) pairs sorted by position
+    pub stops: Vec<(f64, Vec3)>,
+}
+
+impl ColorRamp {
+    pub fn color(&self, t: f64) -> Vec3 {
+        match self.stops.iter().position(|(pos, _)| *pos > t) {
+            None => self.stops.last().map_or(Vec3::zero(), |s| s.1),
+            Some(0) => self.stops[0].1,
+            Some(i) => {
+                let (p0, c0) = self.stops[i - 1];
+                let (p1, c1) = self.stops[i];
+                let f = (t - p0) / (p1 - p0);
+                (1.0 - f) * c0 + f * c1
+            }
+        }
+    }
+}
+
+impl Texture for ColorRamp {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        self.color(self.input.value(u, v, p).x)
+    }
+
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, fp: &Footprint) -> Vec3 {
+        self.color(self.input.value_filtered(u, v, p, fp).x)
+    }
+}
+
+/// One of the three channels of a color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    R,
+    G,
+    B,
+}
+
+impl Channel {
+    pub fn of(self, c: Vec3) -> f64 {
+        match self {
+            Channel::R => c.x,
+            Channel::G => c.y,
+            Channel::B => c.z,
+        }
+    }
+}
+
+/// A single channel of a texture as a gray value
+#[derive(Debug, Clone)]
+pub struct ChannelTexture {
+    pub texture: Arc<dyn Texture>,
+    pub channel: Channel,
+}
+
+impl Texture for ChannelTexture {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        Vec3::splat(self.channel.of(self.texture.value(u, v, p)))
+    }
+
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, fp: &Footprint) -> Vec3 {
+        Vec3::splat(self.channel.of(self.texture.value_filtered(u, v, p, fp)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::SolidColor;
+
+    fn solid(c: f64) -> Arc<dyn Texture> {
+        Arc::new(SolidColor {
+            color_value: Vec3::splat(c),
+        })
+    }
+
+    #[test]
+    fn arithmetic() {
+        let p = Vec3::zero();
+        let mul = Multiply {
+            a: solid(0.5),
+            b: solid(0.4),
+        };
+        assert_eq!(mul.value(0.0, 0.0, p), Vec3::splat(0.2));
+
+        let lerp = Lerp {
+            a: solid(0.0),
+            b: solid(1.0),
+            mask: solid(0.25),
+        };
+        assert_eq!(lerp.value(0.0, 0.0, p), Vec3::splat(0.25));
+
+        let remap = Remap {
+            texture: solid(0.5),
+            from_min: 0.25,
+            from_max: 0.75,
+            to_min: 1.0,
+            to_max: 3.0,
+        };
+        assert_eq!(remap.value(0.0, 0.0, p), Vec3::splat(2.0));
+
+        let step = Remap {
+            from_min: 0.5,
+            from_max: 0.5,
+            ..remap
+        };
+        assert_eq!(step.value(0.0, 0.0, p), Vec3::splat(3.0));
+        assert_eq!(step.remap(0.4), 1.0);
+    }
+
+    #[test]
+    fn ramp() {
+        let ramp = ColorRamp {
+            input: solid(0.0),
+            stops: vec![(0.25, Vec3::zero()), (0.75, Vec3::splat(1.0))],
+        };
+        assert_eq!(ramp.color(0.0), Vec3::zero());
+        assert_eq!(ramp.color(0.5), Vec3::splat(0.5));
+        assert_eq!(ramp.color(0.9), Vec3::splat(1.0));
+    }
+}
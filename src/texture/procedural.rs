@@ -0,0 +1,386 @@
+use std::f64::consts::PI;
+
+use super::{Footprint, Texture, TextureSpace};
+use crate::geometry::Vec3;
+use crate::perlin::Perlin;
+use crate::rng;
+
+// The noise based textures return gray values in [0, 1] so they can drive
+// masks or be colored by other textures.
+
+/// Fractal Brownian motion
+#[derive(Debug)]
+pub struct Fbm {
+    pub noise: Perlin,
+    pub scale: f64,
+    pub octaves: usize,
+    pub lacunarity: f64,
+    pub gain: f64,
+}
+
+impl Fbm {
+    fn eval(&self, p: Vec3, octaves: usize) -> Vec3 {
+        let n = self
+            .noise
+            .fbm(self.scale * p, octaves, self.lacunarity, self.gain);
+        Vec3::splat((0.5 + 0.5 * n).clamp(0.0, 1.0))
+    }
+}
+
+impl Texture for Fbm {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        self.eval(p, self.octaves)
+    }
+
+    fn value_filtered(&self, _u: f64, _v: f64, p: Vec3, fp: &Footprint) -> Vec3 {
+        let octaves = band_limit(self.octaves, self.scale, self.lacunarity, fp);
+        self.eval(p, octaves)
+    }
+}
+
+/// Ridged multifractal, sharp creases where the noise crosses zero.
+/// Each octave is weighted by the previous one so ridges get detail and valleys stay smooth.
+#[derive(Debug)]
+pub struct RidgedMultifractal {
+    pub noise: Perlin,
+    pub scale: f64,
+    pub octaves: usize,
+    pub lacunarity: f64,
+    pub gain: f64,
+    /// Height of the ridges, usually 1
+    pub offset: f64,
+}
+
+impl RidgedMultifractal {
+    fn eval(&self, p: Vec3, octaves: usize) -> Vec3 {
+        let mut q = self.scale * p;
+        let mut sum = 0.0;
+        let mut norm = 0.0;
+        let mut amplitude = 1.0;
+        let mut weight = 1.0;
+
+        for _ in 0..octaves {
+            let mut signal = self.offset - self.noise.noise(q).abs();
+            signal = signal * signal * weight;
+            weight = (2.0 * signal).clamp(0.0, 1.0);
+
+            sum += amplitude * signal;
+            norm += amplitude * self.offset * self.offset;
+            amplitude *= self.gain;
+            q *= self.lacunarity;
+        }
+
+        Vec3::splat((sum / norm).clamp(0.0, 1.0))
+    }
+}
+
+impl Texture for RidgedMultifractal {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        self.eval(p, self.octaves)
+    }
+
+    fn value_filtered(&self, _u: f64, _v: f64, p: Vec3, fp: &Footprint) -> Vec3 {
+        let octaves = band_limit(self.octaves, self.scale, self.lacunarity, fp);
+        self.eval(p, octaves)
+    }
+}
+
+/// Veins along z distorted by turbulence
+#[derive(Debug)]
+pub struct Marble {
+    pub noise: Perlin,
+    /// Frequency of the veins
+    pub scale: f64,
+    /// Strength of the distortion
+    pub turbulence: f64,
+    pub octaves: usize,
+}
+
+impl Marble {
+    fn eval(&self, p: Vec3, octaves: usize) -> f64 {
+        let phase = self.scale * p.z + self.turbulence * self.noise.turb(p, octaves);
+        0.5 * (1.0 + phase.sin())
+    }
+}
+
+impl Texture for Marble {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        Vec3::splat(self.eval(p, self.octaves))
+    }
+
+    fn value_filtered(&self, _u: f64, _v: f64, p: Vec3, fp: &Footprint) -> Vec3 {
+        let octaves = band_limit(self.octaves, 1.0, 2.0, fp);
+        let fade = fade((self.scale + self.turbulence) / (2.0 * PI), fp);
+        Vec3::splat((1.0 - fade) * self.eval(p, octaves) + fade * 0.5)
+    }
+}
+
+/// Growth rings around the y axis, each ring ramping from 0 to 1
+#[derive(Debug)]
+pub struct Wood {
+    pub noise: Perlin,
+    /// Rings per unit of radius
+    pub scale: f64,
+    /// Strength of the ring distortion, in rings
+    pub turbulence: f64,
+    pub octaves: usize,
+}
+
+impl Wood {
+    fn eval(&self, p: Vec3, octaves: usize) -> f64 {
+        let r = (p.x * p.x + p.z * p.z).sqrt();
+        let rings = self.scale * r + self.turbulence * self.noise.turb(p, octaves);
+        rings - rings.floor()
+    }
+}
+
+impl Texture for Wood {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        Vec3::splat(self.eval(p, self.octaves))
+    }
+
+    fn value_filtered(&self, _u: f64, _v: f64, p: Vec3, fp: &Footprint) -> Vec3 {
+        let octaves = band_limit(self.octaves, 1.0, 2.0, fp);
+        let fade = fade(self.scale, fp);
+        Vec3::splat((1.0 - fade) * self.eval(p, octaves) + fade * 0.5)
+    }
+}
+
+/// Which distance a `Worley` texture outputs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorleyFeature {
+    /// Distance to the closest feature point, round cells
+    F1,
+    /// Distance to the second closest feature point
+    F2,
+    /// Difference of the two, bright cell borders
+    F2MinusF1,
+}
+
+/// Cellular noise, one random feature point per unit cell
+#[derive(Debug)]
+pub struct Worley {
+    pub seed: u32,
+    pub scale: f64,
+    pub feature: WorleyFeature,
+}
+
+impl Worley {
+    /// Distances to the closest and second closest feature points
+    pub fn distances(&self, p: Vec3) -> (f64, f64) {
+        let q = self.scale * p;
+        let base = (q.x.floor() as i64, q.y.floor() as i64, q.z.floor() as i64);
+        let mut f1 = f64::INFINITY;
+        let mut f2 = f64::INFINITY;
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let cell = (base.0 + dx, base.1 + dy, base.2 + dz);
+                    let point = Vec3 {
+                        x: cell.0 as f64 + hash_unit(self.seed, cell, 0),
+                        y: cell.1 as f64 + hash_unit(self.seed, cell, 1),
+                        z: cell.2 as f64 + hash_unit(self.seed, cell, 2),
+                    };
+                    let d = (point - q).len();
+                    if d < f1 {
+                        f2 = f1;
+                        f1 = d;
+                    } else if d < f2 {
+                        f2 = d;
+                    }
+                }
+            }
+        }
+
+        (f1, f2)
+    }
+}
+
+impl Texture for Worley {
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Vec3 {
+        let (f1, f2) = self.distances(p);
+        let d = match self.feature {
+            WorleyFeature::F1 => f1,
+            WorleyFeature::F2 => f2,
+            WorleyFeature::F2MinusF1 => f2 - f1,
+        };
+        Vec3::splat(d.clamp(0.0, 1.0))
+    }
+}
+
+/// Blends from `from` at `start` to `to` at `end`, constant past both ends.
+/// In `Uv` space the points are read as (u, v, 0). `start == end` is all `from`.
+#[derive(Debug)]
+pub struct LinearGradient {
+    pub space: TextureSpace,
+    pub start: Vec3,
+    pub end: Vec3,
+    pub from: Vec3,
+    pub to: Vec3,
+}
+
+impl Texture for LinearGradient {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let q = coords(self.space, u, v, p);
+        let axis = self.end - self.start;
+        if axis.len_sq() == 0.0 {
+            return self.from;
+        }
+        let t = (Vec3::dot(q - self.start, axis) / axis.len_sq()).clamp(0.0, 1.0);
+        (1.0 - t) * self.from + t * self.to
+    }
+}
+
+/// Blends from `inner` at `center` to `outer` at `radius` and beyond.
+/// In `Uv` space the points are read as (u, v, 0). A zero radius is all `outer`.
+#[derive(Debug)]
+pub struct RadialGradient {
+    pub space: TextureSpace,
+    pub center: Vec3,
+    pub radius: f64,
+    pub inner: Vec3,
+    pub outer: Vec3,
+}
+
+impl Texture for RadialGradient {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        if self.radius <= 0.0 {
+            return self.outer;
+        }
+        let q = coords(self.space, u, v, p);
+        let t = ((q - self.center).len() / self.radius).clamp(0.0, 1.0);
+        (1.0 - t) * self.inner + t * self.outer
+    }
+}
+
+fn coords(space: TextureSpace, u: f64, v: f64, p: Vec3) -> Vec3 {
+    match space {
+        TextureSpace::Uv => Vec3 { x: u, y: v, z: 0.0 },
+        TextureSpace::Object => p,
+    }
+}
+
+/// Number of octaves that are still coarser than the footprint. Octave `i`
+/// has a frequency of `scale * lacunarity^i`, only those under half a cycle
+/// per pixel are kept.
+fn band_limit(octaves: usize, scale: f64, lacunarity: f64, fp: &Footprint) -> usize {
+    if fp.width <= 0.0 || lacunarity <= 1.0 {
+        return octaves;
+    }
+
+    let limit = (0.5 / (scale * fp.width)).ln() / lacunarity.ln();
+    (limit.floor() + 1.0).clamp(1.0, octaves.max(1) as f64) as usize
+}
+
+/// How far to fade a pattern of `frequency` cycles per unit towards its mean
+fn fade(frequency: f64, fp: &Footprint) -> f64 {
+    (2.0 * frequency * fp.width - 0.5).clamp(0.0, 1.0)
+}
+
+/// Hashes an integer cell and channel to [0, 1)
+fn hash_unit(seed: u32, cell: (i64, i64, i64), channel: u64) -> f64 {
+    rng::hash_unit(&[
+        seed as u64,
+        cell.0 as u64,
+        cell.1 as u64,
+        cell.2 as u64,
+        channel,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_noise() {
+        let p = Vec3 {
+            x: 0.3,
+            y: 1.7,
+            z: -2.2,
+        };
+        let a = Perlin::with_seed(1).noise(p);
+        assert_eq!(a, Perlin::with_seed(1).noise(p));
+        assert_ne!(a, Perlin::with_seed(2).noise(p));
+    }
+
+    #[test]
+    fn worley() {
+        let w = Worley {
+            seed: 7,
+            scale: 3.0,
+            feature: WorleyFeature::F1,
+        };
+        for i in 0..100 {
+            let p = Vec3::splat(i as f64 * 0.137);
+            let (f1, f2) = w.distances(p);
+            assert!(f1 <= f2);
+            assert!(f1 < 3.0f64.sqrt());
+        }
+
+        let other = Worley { seed: 8, ..w };
+        let p = Vec3::splat(0.4);
+        assert_ne!(w.distances(p), other.distances(p));
+    }
+
+    #[test]
+    fn gradients() {
+        let linear = LinearGradient {
+            space: TextureSpace::Uv,
+            start: Vec3::zero(),
+            end: Vec3 {
+                x: 1.0,
+                y: 0.0,
+                z: 0.0,
+            },
+            from: Vec3::zero(),
+            to: Vec3::splat(1.0),
+        };
+        assert_eq!(linear.value(0.25, 0.9, Vec3::zero()), Vec3::splat(0.25));
+        assert_eq!(linear.value(2.0, 0.0, Vec3::zero()), Vec3::splat(1.0));
+
+        let radial = RadialGradient {
+            space: TextureSpace::Object,
+            center: Vec3::zero(),
+            radius: 2.0,
+            inner: Vec3::splat(1.0),
+            outer: Vec3::zero(),
+        };
+        assert_eq!(
+            radial.value(
+                0.0,
+                0.0,
+                Vec3 {
+                    x: 0.0,
+                    y: 1.0,
+                    z: 0.0
+                }
+            ),
+            Vec3::splat(0.5)
+        );
+
+        // Degenerate gradients pick an end instead of dividing by zero
+        let point = LinearGradient {
+            end: Vec3::zero(),
+            ..linear
+        };
+        assert_eq!(point.value(0.5, 0.5, Vec3::zero()), Vec3::zero());
+        let dot = RadialGradient {
+            radius: 0.0,
+            ..radial
+        };
+        assert_eq!(dot.value(0.0, 0.0, Vec3::zero()), Vec3::zero());
+    }
+
+    #[test]
+    fn band_limited() {
+        let fp = |width| Footprint {
+            width,
+            uv_width: 0.0,
+        };
+        assert_eq!(band_limit(8, 1.0, 2.0, &fp(0.0)), 8);
+        assert_eq!(band_limit(8, 1.0, 2.0, &fp(0.1)), 3);
+        assert_eq!(band_limit(8, 1.0, 2.0, &fp(10.0)), 1);
+    }
+}
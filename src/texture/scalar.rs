@@ -0,0 +1,113 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use super::{Channel, Footprint, Texture};
+use crate::geometry::Vec3;
+
+/// A texture returning a single number, for material parameters such as
+/// roughness or an index of refraction. A plain `f64` is a constant one.
+pub trait ScalarTexture: Send + Sync + Debug {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> f64;
+
+    /// Value averaged over the area a pixel covers. Defaults to point sampling.
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, _fp: &Footprint) -> f64 {
+        self.value(u, v, p)
+    }
+}
+
+impl ScalarTexture for f64 {
+    fn value(&self, _u: f64, _v: f64, _p: Vec3) -> f64 {
+        *self
+    }
+}
+
+/// Relative luminance of a linear color texture, using the Rec. 709 weights
+#[derive(Debug, Clone)]
+pub struct Luminance {
+    pub texture: Arc<dyn Texture>,
+}
+
+impl ScalarTexture for Luminance {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> f64 {
+        self.texture.value(u, v, p).luminance()
+    }
+
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, fp: &Footprint) -> f64 {
+        self.texture.value_filtered(u, v, p, fp).luminance()
+    }
+}
+
+/// One channel of a color texture
+#[derive(Debug, Clone)]
+pub struct ScalarChannel {
+    pub texture: Arc<dyn Texture>,
+    pub channel: Channel,
+}
+
+impl ScalarTexture for ScalarChannel {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> f64 {
+        self.channel.of(self.texture.value(u, v, p))
+    }
+
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, fp: &Footprint) -> f64 {
+        self.channel.of(self.texture.value_filtered(u, v, p, fp))
+    }
+}
+
+/// Linearly maps a scalar texture from [0, 1] to [min, max]
+#[derive(Debug, Clone)]
+pub struct ScalarRange {
+    pub texture: Arc<dyn ScalarTexture>,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl ScalarTexture for ScalarRange {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> f64 {
+        self.min + self.texture.value(u, v, p) * (self.max - self.min)
+    }
+
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, fp: &Footprint) -> f64 {
+        self.min + self.texture.value_filtered(u, v, p, fp) * (self.max - self.min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::SolidColor;
+
+    #[test]
+    fn adapters() {
+        let p = Vec3::zero();
+        let color: Arc<dyn Texture> = Arc::new(SolidColor {
+            color_value: Vec3 {
+                x: 0.25,
+                y: 0.5,
+                z: 1.0,
+            },
+        });
+
+        assert_eq!(0.3.value(0.0, 0.0, p), 0.3);
+
+        let gray = Luminance {
+            texture: Arc::new(SolidColor {
+                color_value: Vec3::splat(0.5),
+            }),
+        };
+        assert!((gray.value(0.0, 0.0, p) - 0.5).abs() < 1e-12);
+
+        let green = ScalarChannel {
+            texture: color,
+            channel: Channel::G,
+        };
+        assert_eq!(green.value(0.0, 0.0, p), 0.5);
+
+        let range = ScalarRange {
+            texture: Arc::new(green),
+            min: 1.0,
+            max: 2.0,
+        };
+        assert_eq!(range.value(0.0, 0.0, p), 1.5);
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use super::{Footprint, Texture};
+use crate::geometry::Vec3;
+
+/// Coordinates a `TextureTransform` operates on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureSpace {
+    /// The surface `u`, `v`
+    Uv,
+    /// The hit point `p`
+    Object,
+}
+
+/// Scales, rotates then offsets the coordinates a texture is looked up with.
+///
+/// In `Uv` space only the x and y components of `scale` and `offset` are used
+/// and `rotation` turns around the uv origin. In `Object` space `rotation`
+/// turns around the y axis.
+#[derive(Debug, Clone)]
+pub struct TextureTransform {
+    pub texture: Arc<dyn Texture>,
+    pub space: TextureSpace,
+    pub scale: Vec3,
+    /// Radians
+    pub rotation: f64,
+    pub offset: Vec3,
+}
+
+impl TextureTransform {
+    /// The identity transform in `space`
+    pub fn new(texture: Arc<dyn Texture>, space: TextureSpace) -> Self {
+        Self {
+            texture,
+            space,
+            scale: Vec3::splat(1.0),
+            rotation: 0.0,
+            offset: Vec3::zero(),
+        }
+    }
+
+    fn apply(&self, u: f64, v: f64, p: Vec3) -> (f64, f64, Vec3) {
+        let (sin, cos) = self.rotation.sin_cos();
+        match self.space {
+            TextureSpace::Uv => (
+                self.scale.x * (cos * u - sin * v) + self.offset.x,
+                self.scale.y * (sin * u + cos * v) + self.offset.y,
+                p,
+            ),
+            TextureSpace::Object => {
+                let rotated = Vec3 {
+                    x: cos * p.x + sin * p.z,
+                    y: p.y,
+                    z: -sin * p.x + cos * p.z,
+                };
+                (u, v, self.scale * rotated + self.offset)
+            }
+        }
+    }
+
+    /// Largest stretch applied by `scale`, used to resize footprints
+    fn stretch(&self) -> f64 {
+        match self.space {
+            TextureSpace::Uv => self.scale.x.abs().max(self.scale.y.abs()),
+            TextureSpace::Object => self
+                .scale
+                .x
+                .abs()
+                .max(self.scale.y.abs())
+                .max(self.scale.z.abs()),
+        }
+    }
+}
+
+impl Texture for TextureTransform {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Vec3 {
+        let (u, v, p) = self.apply(u, v, p);
+        self.texture.value(u, v, p)
+    }
+
+    fn value_filtered(&self, u: f64, v: f64, p: Vec3, fp: &Footprint) -> Vec3 {
+        let (u, v, p) = self.apply(u, v, p);
+        let fp = match self.space {
+            TextureSpace::Uv => Footprint {
+                uv_width: fp.uv_width * self.stretch(),
+                ..*fp
+            },
+            TextureSpace::Object => Footprint {
+                width: fp.width * self.stretch(),
+                ..*fp
+            },
+        };
+        self.texture.value_filtered(u, v, p, &fp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::{SolidColor, UvChecker};
+
+    fn checker() -> Arc<dyn Texture> {
+        Arc::new(UvChecker {
+            odd: Box::new(SolidColor {
+                color_value: Vec3::zero(),
+            }),
+            even: Box::new(SolidColor {
+                color_value: Vec3::splat(1.0),
+            }),
+            tiles_u: 2.0,
+            tiles_v: 2.0,
+        })
+    }
+
+    #[test]
+    fn uv_scale_offset() {
+        let mut t = TextureTransform::new(checker(), TextureSpace::Uv);
+        assert_eq!(t.value(0.25, 0.25, Vec3::zero()), Vec3::splat(1.0));
+        assert_eq!(t.value(0.75, 0.25, Vec3::zero()), Vec3::zero());
+
+        t.offset.x = 0.5;
+        assert_eq!(t.value(0.25, 0.25, Vec3::zero()), Vec3::zero());
+
+        t.offset.x = 0.0;
+        t.scale = Vec3::splat(2.0);
+        assert_eq!(t.value(0.375, 0.125, Vec3::zero()), Vec3::zero());
+    }
+
+    #[test]
+    fn uv_rotation() {
+        let mut t = TextureTransform::new(checker(), TextureSpace::Uv);
+        t.rotation = std::f64::consts::FRAC_PI_2;
+        // (0.25, 0.25) is rotated to (-0.25, 0.25), an odd tile
+        assert_eq!(t.value(0.25, 0.25, Vec3::zero()), Vec3::zero());
+    }
+
+    #[test]
+    fn filtered_checker() {
+        let fp = Footprint {
+            width: 0.0,
+            uv_width: 10.0,
+        };
+        let c = checker().value_filtered(0.3, 0.6, Vec3::zero(), &fp);
+        assert!((c - Vec3::splat(0.5)).len() < 0.05);
+    }
+}